@@ -1,88 +1,558 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
-fn parse_locations(file_contents: &str) -> (Vec<u32>, Vec<u32>) {
+/// How the lists are sorted and the similarity score counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Strategy {
+    /// `sort_unstable` and a `HashMap` of counts.
+    #[default]
+    Comparison,
+    /// Radix sort and a merge over the sorted lists, linear in the list length.
+    Radix,
+}
+
+/// The two location lists in input order, along with sorted copies for the distance metrics.
+/// Metrics over pairs ignore any excess entries in the longer list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LocationLists {
+    left: Vec<u64>,
+    right: Vec<u64>,
+    sorted_left: Vec<u64>,
+    sorted_right: Vec<u64>,
+    strategy: Strategy,
+}
+
+impl LocationLists {
+    fn new(left: Vec<u64>, right: Vec<u64>) -> Self {
+        Self::with_strategy(left, right, Strategy::default())
+    }
+
+    fn with_strategy(left: Vec<u64>, right: Vec<u64>, strategy: Strategy) -> Self {
+        let mut sorted_left = left.clone();
+        let mut sorted_right = right.clone();
+        match strategy {
+            Strategy::Comparison => {
+                sorted_left.sort_unstable();
+                sorted_right.sort_unstable();
+            }
+            Strategy::Radix => {
+                radix_sort(&mut sorted_left);
+                radix_sort(&mut sorted_right);
+            }
+        }
+        LocationLists {
+            left,
+            right,
+            sorted_left,
+            sorted_right,
+            strategy,
+        }
+    }
+
+    fn total_distance(&self) -> u128 {
+        calculate_total_distance(&self.sorted_left, &self.sorted_right)
+    }
+
+    fn similarity_score(&self) -> u128 {
+        match self.strategy {
+            Strategy::Comparison => {
+                calculate_similarity_score(&self.sorted_left, &self.sorted_right)
+            }
+            Strategy::Radix => merge_similarity_score(&self.sorted_left, &self.sorted_right),
+        }
+    }
+
+    /// Sum of squared differences between the sorted lists, `None` if it overflows.
+    fn squared_distance(&self) -> Option<u128> {
+        self.sorted_pairs()
+            .map(|(l, r)| (r.abs_diff(l) as u128).pow(2))
+            .try_fold(0u128, |sum, square| sum.checked_add(square))
+    }
+
+    /// Largest difference between the sorted lists.
+    fn max_distance(&self) -> u64 {
+        self.sorted_pairs()
+            .map(|(l, r)| r.abs_diff(l))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Spearman's rank correlation between the lists as paired in the input, with tied values
+    /// given their average rank. `None` if either list has fewer than two distinct values.
+    fn spearman(&self) -> Option<f64> {
+        let n = self.left.len().min(self.right.len());
+        let left_ranks = ranks(&self.left[..n]);
+        let right_ranks = ranks(&self.right[..n]);
+        let mean = (n as f64 + 1.0) / 2.0;
+        let (mut covariance, mut left_variance, mut right_variance) = (0.0, 0.0, 0.0);
+        for (l, r) in left_ranks.iter().zip(&right_ranks) {
+            covariance += (l - mean) * (r - mean);
+            left_variance += (l - mean).powi(2);
+            right_variance += (r - mean).powi(2);
+        }
+        let denominator = (left_variance * right_variance).sqrt();
+        (denominator > 0.0).then(|| covariance / denominator)
+    }
+
+    /// Kendall's tau-b between the lists as paired in the input, which accounts for ties.
+    /// `None` if either list has fewer than two distinct values.
+    fn kendall_tau(&self) -> Option<f64> {
+        let pairs: Vec<(u64, u64)> = self.pairs().collect();
+        let (mut concordant, mut discordant) = (0i64, 0i64);
+        let (mut left_ties, mut right_ties) = (0i64, 0i64);
+        for (i, &(l1, r1)) in pairs.iter().enumerate() {
+            for &(l2, r2) in &pairs[i + 1..] {
+                match (l1.cmp(&l2), r1.cmp(&r2)) {
+                    (Ordering::Equal, Ordering::Equal) => {
+                        left_ties += 1;
+                        right_ties += 1;
+                    }
+                    (Ordering::Equal, _) => left_ties += 1,
+                    (_, Ordering::Equal) => right_ties += 1,
+                    (a, b) if a == b => concordant += 1,
+                    _ => discordant += 1,
+                }
+            }
+        }
+        let total = (pairs.len() * pairs.len().saturating_sub(1) / 2) as i64;
+        let denominator = (((total - left_ties) * (total - right_ties)) as f64).sqrt();
+        (denominator > 0.0).then(|| (concordant - discordant) as f64 / denominator)
+    }
+
+    /// Locations in both lists, counting repeats as many times as they appear in both.
+    fn intersection(&self) -> Vec<u64> {
+        merge_multisets(&self.sorted_left, &self.sorted_right).0
+    }
+
+    /// Locations in the left list left over once those in the right list are taken out.
+    fn difference(&self) -> Vec<u64> {
+        merge_multisets(&self.sorted_left, &self.sorted_right).1
+    }
+
+    fn pairs(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.left.iter().copied().zip(self.right.iter().copied())
+    }
+
+    fn sorted_pairs(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.sorted_left
+            .iter()
+            .copied()
+            .zip(self.sorted_right.iter().copied())
+    }
+}
+
+/// Ranks from 1, tied values share the average of the ranks they span.
+fn ranks(values: &[u64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_unstable_by_key(|&i| values[i]);
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Walks two sorted lists together, returning their multiset intersection and `left - right`.
+fn merge_multisets(left: &[u64], right: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    let (mut intersection, mut difference) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() {
+        match right.get(j).map(|r| left[i].cmp(r)) {
+            Some(Ordering::Equal) => {
+                intersection.push(left[i]);
+                i += 1;
+                j += 1;
+            }
+            Some(Ordering::Greater) => j += 1,
+            Some(Ordering::Less) | None => {
+                difference.push(left[i]);
+                i += 1;
+            }
+        }
+    }
+    (intersection, difference)
+}
+
+#[derive(Debug, PartialEq)]
+enum LocationsError {
+    /// A line doesn't have exactly two columns, lines are numbered from 1.
+    Columns {
+        line: usize,
+        found: usize,
+    },
+    InvalidNumber {
+        line: usize,
+        token: String,
+    },
+}
+
+impl fmt::Display for LocationsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocationsError::Columns { line, found } => {
+                write!(f, "line {line} has {found} columns, expected 2")
+            }
+            LocationsError::InvalidNumber { line, token } => {
+                write!(f, "line {line} has invalid number {token:?}")
+            }
+        }
+    }
+}
+
+impl Error for LocationsError {}
+
+/// Parses two whitespace separated columns of numbers of any type, accepting `\n` or `\r\n`
+/// line endings and a single trailing newline. The lists are returned in input order.
+fn parse_columns<T: FromStr>(file_contents: &str) -> Result<(Vec<T>, Vec<T>), LocationsError> {
     let mut left = Vec::new();
     let mut right = Vec::new();
-    let mut is_left = true;
-    for location in file_contents.split_whitespace() {
-        let location_id = location.parse::<u32>().unwrap();
-        if is_left {
-            left.push(location_id);
-        } else {
-            right.push(location_id);
+    for (index, text) in file_contents.lines().enumerate() {
+        let line = index + 1;
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let &[l, r] = tokens.as_slice() else {
+            return Err(LocationsError::Columns {
+                line,
+                found: tokens.len(),
+            });
+        };
+        let parse = |token: &str| {
+            token
+                .parse::<T>()
+                .map_err(|_| LocationsError::InvalidNumber {
+                    line,
+                    token: token.to_string(),
+                })
         };
-        is_left = !is_left;
+        left.push(parse(l)?);
+        right.push(parse(r)?);
     }
-    left.sort_unstable();
-    right.sort_unstable();
-    (left, right)
+    Ok((left, right))
 }
 
-fn calculate_total_distance(locations: &(Vec<u32>, Vec<u32>)) -> usize {
-    locations
-        .0
-        .iter()
-        .zip(&locations.1)
-        .map(|(&l, &r)| r.abs_diff(l) as usize)
+fn parse_locations(file_contents: &str) -> Result<LocationLists, LocationsError> {
+    parse_locations_with(file_contents, Strategy::default())
+}
+
+fn parse_locations_with(
+    file_contents: &str,
+    strategy: Strategy,
+) -> Result<LocationLists, LocationsError> {
+    let (left, right) = parse_columns(file_contents)?;
+    Ok(LocationLists::with_strategy(left, right, strategy))
+}
+
+fn calculate_total_distance(left: &[u64], right: &[u64]) -> u128 {
+    left.iter()
+        .zip(right)
+        .map(|(&l, &r)| r.abs_diff(l) as u128)
         .sum()
 }
 
-fn calculate_similarity_score(locations: &(Vec<u32>, Vec<u32>)) -> usize {
-    let right_counts = locations
-        .1
+fn calculate_similarity_score(left: &[u64], right: &[u64]) -> u128 {
+    let right_counts = right
         .iter()
-        .fold(HashMap::<u32, usize>::new(), |mut acc, &x| {
+        .fold(HashMap::<u64, usize>::new(), |mut acc, &x| {
             acc.entry(x).and_modify(|e| *e += 1).or_insert(1);
             acc
         });
-    locations
-        .0
-        .iter()
-        .map(|&x| x as usize)
-        .fold(0, |acc: usize, x: usize| {
-            acc + x * right_counts.get(&(x as u32)).unwrap_or(&0)
-        })
+    left.iter().fold(0, |acc: u128, &x| {
+        acc + x as u128 * *right_counts.get(&x).unwrap_or(&0) as u128
+    })
+}
+
+/// LSD radix sort a byte at a time. Passes where every value shares the byte are skipped, so
+/// bounded ids like the 5 digit locations only take three of the eight.
+fn radix_sort(values: &mut Vec<u64>) {
+    let mut buffer = vec![0; values.len()];
+    for shift in (0..64).step_by(8) {
+        let mut counts = [0usize; 256];
+        for &value in values.iter() {
+            counts[(value >> shift) as usize & 0xff] += 1;
+        }
+        if counts.contains(&values.len()) {
+            continue;
+        }
+        let mut offset = 0;
+        for count in counts.iter_mut() {
+            let next = offset + *count;
+            *count = offset;
+            offset = next;
+        }
+        for &value in values.iter() {
+            let digit = (value >> shift) as usize & 0xff;
+            buffer[counts[digit]] = value;
+            counts[digit] += 1;
+        }
+        std::mem::swap(values, &mut buffer);
+    }
+}
+
+/// Similarity score over two sorted lists, counting each run of equal values in both.
+fn merge_similarity_score(left: &[u64], right: &[u64]) -> u128 {
+    let (mut i, mut j) = (0, 0);
+    let mut score = 0;
+    while i < left.len() && j < right.len() {
+        match left[i].cmp(&right[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                let value = left[i];
+                let left_run = left[i..].iter().take_while(|&&x| x == value).count();
+                let right_run = right[j..].iter().take_while(|&&x| x == value).count();
+                score += value as u128 * (left_run * right_run) as u128;
+                i += left_run;
+                j += right_run;
+            }
+        }
+    }
+    score
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::load_input_for_day;
+    use crate::utils::{bench, load_input_for_day, SplitMix64};
+
+    fn random_locations(rng: &mut SplitMix64, len: usize) -> Vec<u64> {
+        (0..len).map(|_| 10000 + rng.below(90000) as u64).collect()
+    }
 
     #[test]
     fn test_parse_locations() {
-        let out = parse_locations(&"123 654\n321 456");
-        assert_eq!(out.0, vec![123, 4321]);
-        assert_eq!(out.1, vec![456, 654]);
+        let out = parse_locations("123 654\n321 456").unwrap();
+        assert_eq!(out.sorted_left, vec![123, 321]);
+        assert_eq!(out.sorted_right, vec![456, 654]);
+        assert_eq!(out.left, vec![123, 321]);
+        assert_eq!(out.right, vec![654, 456]);
+    }
+
+    #[test]
+    fn test_parse_locations_line_endings() {
+        let out = parse_locations("3 4\r\n12345\t6\r\n").unwrap();
+        assert_eq!(out.sorted_left, vec![3, 12345]);
+        assert_eq!(out.sorted_right, vec![4, 6]);
+        assert!(parse_locations("").unwrap().left.is_empty());
+    }
+
+    #[test]
+    fn test_parse_locations_invalid() {
+        assert_eq!(
+            parse_locations("1 2\n3\n5 6").err(),
+            Some(LocationsError::Columns { line: 2, found: 1 })
+        );
+        assert_eq!(
+            parse_locations("1 2\n3 4 5").err(),
+            Some(LocationsError::Columns { line: 2, found: 3 })
+        );
+        assert_eq!(
+            parse_locations("1 2\n\n3 4").err(),
+            Some(LocationsError::Columns { line: 2, found: 0 })
+        );
+        assert_eq!(
+            parse_locations("1 2\n3 4\n5 x6").unwrap_err().to_string(),
+            "line 3 has invalid number \"x6\""
+        );
+        assert_eq!(
+            parse_locations("1 -2").unwrap_err().to_string(),
+            "line 1 has invalid number \"-2\""
+        );
+        assert_eq!(
+            parse_locations("1 2\n3 4\n\n").unwrap_err().to_string(),
+            "line 3 has 0 columns, expected 2"
+        );
+    }
+
+    #[test]
+    fn test_parse_columns_widths() {
+        assert_eq!(
+            parse_columns::<u64>("5000000000 1\n2 18446744073709551615"),
+            Ok((vec![5000000000, 2], vec![1, u64::MAX]))
+        );
+        assert_eq!(
+            parse_columns::<i128>("-170141183460469231731687303715884105728 -1"),
+            Ok((vec![i128::MIN], vec![-1]))
+        );
+        assert_eq!(
+            parse_columns::<u32>("1 5000000000").err(),
+            Some(LocationsError::InvalidNumber {
+                line: 1,
+                token: "5000000000".to_string()
+            })
+        );
     }
 
     #[test]
     fn test_calculate_total_distance() {
-        let locations = (vec![1, 2, 5], vec![1, 3, 4]);
-        let total = calculate_total_distance(&locations);
+        let total = calculate_total_distance(&[1, 2, 5], &[1, 3, 4]);
         assert_eq!(total, 2);
     }
 
     #[test]
     fn test_calculate_similarity_score() {
-        let locations = (vec![1, 2, 3, 3, 3, 4], vec![3, 3, 3, 4, 5, 9]);
-        let similarity = calculate_similarity_score(&locations);
+        let similarity = calculate_similarity_score(&[1, 2, 3, 3, 3, 4], &[3, 3, 3, 4, 5, 9]);
         assert_eq!(similarity, 31);
     }
 
+    fn example() -> LocationLists {
+        parse_locations("3   4\n4   3\n2   5\n1   3\n3   9\n3   3").unwrap()
+    }
+
+    #[test]
+    fn test_location_lists_distances() {
+        let locations = example();
+        assert_eq!(locations.total_distance(), 11);
+        assert_eq!(locations.similarity_score(), 31);
+        assert_eq!(locations.squared_distance(), Some(35));
+        assert_eq!(locations.max_distance(), 5);
+    }
+
+    #[test]
+    fn test_location_lists_correlation() {
+        let locations = example();
+        assert!((locations.spearman().unwrap() + 3.0 / 31.0).abs() < 1e-12);
+        assert!((locations.kendall_tau().unwrap() + 1.0 / 12.0).abs() < 1e-12);
+        let increasing = LocationLists::new(vec![1, 2, 3], vec![10, 20, 30]);
+        assert_eq!(increasing.spearman(), Some(1.0));
+        assert_eq!(increasing.kendall_tau(), Some(1.0));
+        let decreasing = LocationLists::new(vec![1, 2, 3], vec![30, 20, 10]);
+        assert_eq!(decreasing.spearman(), Some(-1.0));
+        assert_eq!(decreasing.kendall_tau(), Some(-1.0));
+        let constant = LocationLists::new(vec![1, 2, 3], vec![5, 5, 5]);
+        assert_eq!(constant.spearman(), None);
+        assert_eq!(constant.kendall_tau(), None);
+    }
+
+    #[test]
+    fn test_location_lists_multisets() {
+        let locations = example();
+        assert_eq!(locations.intersection(), vec![3, 3, 3, 4]);
+        assert_eq!(locations.difference(), vec![1, 2]);
+        let locations = LocationLists::new(vec![5, 1, 5, 7], vec![5, 2]);
+        assert_eq!(locations.intersection(), vec![5]);
+        assert_eq!(locations.difference(), vec![1, 5, 7]);
+    }
+
+    #[test]
+    fn test_radix_sort() {
+        let mut rng = SplitMix64::new(1);
+        for len in [0, 1, 2, 100, 5000] {
+            let mut values = random_locations(&mut rng, len);
+            values.extend((0..len / 10).map(|_| rng.next_u64()));
+            values.extend((0..len / 10).map(|_| rng.next_u64() >> 32));
+            values.push(u64::MAX);
+            let mut expected = values.clone();
+            expected.sort_unstable();
+            radix_sort(&mut values);
+            assert_eq!(values, expected);
+        }
+        let mut same = vec![7; 10];
+        radix_sort(&mut same);
+        assert_eq!(same, vec![7; 10]);
+    }
+
+    #[test]
+    fn test_merge_similarity_score() {
+        assert_eq!(
+            merge_similarity_score(&[1, 2, 3, 3, 3, 4], &[3, 3, 3, 4, 5, 9]),
+            31
+        );
+        assert_eq!(merge_similarity_score(&[], &[1]), 0);
+        let mut rng = SplitMix64::new(2);
+        let mut left: Vec<u64> = (0..2000).map(|_| rng.below(300) as u64).collect();
+        let mut right: Vec<u64> = (0..1500).map(|_| rng.below(300) as u64).collect();
+        left.sort_unstable();
+        right.sort_unstable();
+        assert_eq!(
+            merge_similarity_score(&left, &right),
+            calculate_similarity_score(&left, &right)
+        );
+    }
+
+    #[test]
+    fn test_wide_locations() {
+        let input = "5000000000 18446744073709551615\n\
+                     18446744073709551615 5000000000\n\
+                     18446744073709551615 1";
+        for strategy in [Strategy::Comparison, Strategy::Radix] {
+            let locations = parse_locations_with(input, strategy).unwrap();
+            assert_eq!(locations.sorted_left, vec![5000000000, u64::MAX, u64::MAX]);
+            assert_eq!(locations.sorted_right, vec![1, 5000000000, u64::MAX]);
+            assert_eq!(
+                locations.total_distance(),
+                5000000000 - 1 + u64::MAX as u128 - 5000000000
+            );
+            assert_eq!(
+                locations.similarity_score(),
+                5000000000 + 2 * u64::MAX as u128
+            );
+            assert_eq!(locations.max_distance(), u64::MAX - 5000000000);
+            assert_eq!(
+                locations.squared_distance(),
+                Some(4999999999u128.pow(2) + (u64::MAX as u128 - 5000000000).pow(2))
+            );
+        }
+        let far = LocationLists::new(vec![0, 1], vec![u64::MAX, u64::MAX]);
+        assert_eq!(far.squared_distance(), None);
+    }
+
+    #[test]
+    fn test_strategies_match() {
+        let file_contents = load_input_for_day(1);
+        let comparison = parse_locations_with(&file_contents, Strategy::Comparison).unwrap();
+        let radix = parse_locations_with(&file_contents, Strategy::Radix).unwrap();
+        assert_eq!(radix.sorted_left, comparison.sorted_left);
+        assert_eq!(radix.sorted_right, comparison.sorted_right);
+        assert_eq!(radix.total_distance(), comparison.total_distance());
+        assert_eq!(radix.similarity_score(), comparison.similarity_score());
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_strategies() {
+        let file_contents = load_input_for_day(1);
+        let (left, right) = parse_columns::<u64>(&file_contents).unwrap();
+        let mut rng = SplitMix64::new(2024);
+        let generated_left = random_locations(&mut rng, 10_000_000);
+        let generated_right = random_locations(&mut rng, 10_000_000);
+        for (label, left, right, iterations) in [
+            ("input", &left, &right, 1000),
+            ("10M", &generated_left, &generated_right, 5),
+        ] {
+            for strategy in [Strategy::Comparison, Strategy::Radix] {
+                bench(&format!("{label} {strategy:?}"), iterations, || {
+                    let locations =
+                        LocationLists::with_strategy(left.clone(), right.clone(), strategy);
+                    (locations.total_distance(), locations.similarity_score())
+                });
+            }
+        }
+    }
+
     #[test]
     fn calculate_pt_1() {
         let file_contents = load_input_for_day(1);
-        let locations = parse_locations(file_contents.as_str());
-        let total_distance = calculate_total_distance(&locations);
+        let locations = parse_locations(file_contents.as_str()).unwrap();
+        let total_distance = locations.total_distance();
         println!("total distance is {total_distance:?}");
     }
 
     #[test]
     fn calculate_pt_2() {
         let file_contents = load_input_for_day(1);
-        let locations = parse_locations(file_contents.as_str());
-        let total_similarity = calculate_similarity_score(&locations);
+        let locations = parse_locations(file_contents.as_str()).unwrap();
+        let total_similarity = locations.similarity_score();
         println!("total similarity is {total_similarity:?}");
     }
 }
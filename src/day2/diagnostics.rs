@@ -0,0 +1,138 @@
+use super::{first_failure, is_safe_with_tolerance, Failure, Reports, SafetyPolicy};
+use itertools::Itertools;
+use std::fmt::Write;
+
+/// Where and why a report first breaks the policy, and whether the dampener rescues it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnosis {
+    /// Index of the first level to break the policy and why, `None` if the report is safe.
+    pub failure: Option<(usize, Failure)>,
+    /// The level whose removal makes an unsafe report safe, if there is one.
+    pub dampened_by: Option<usize>,
+}
+
+impl Diagnosis {
+    pub fn is_safe(&self, dampener: bool) -> bool {
+        self.failure.is_none() || (dampener && self.dampened_by.is_some())
+    }
+}
+
+pub fn diagnose(report: &[i32], policy: &SafetyPolicy) -> Diagnosis {
+    let failure = first_failure(report, policy);
+    let dampened_by = failure
+        .and_then(|_| is_safe_with_tolerance(report, 1, policy))
+        .and_then(|removed| removed.first().copied());
+    Diagnosis {
+        failure,
+        dampened_by,
+    }
+}
+
+/// Lists every report that's unsafe without the dampener, one per line, with why it fails and
+/// which removal, if any, rescues it. Reports are numbered from 1, not counting blank lines.
+pub fn audit(reports: &Reports, policy: &SafetyPolicy) -> String {
+    let mut listing = String::new();
+    for (number, report) in reports.iter().enumerate() {
+        let diagnosis = diagnose(report, policy);
+        let Some((index, failure)) = diagnosis.failure else {
+            continue;
+        };
+        let levels = if index > 0 && failure != Failure::OutOfBounds {
+            format!("{} -> {}", report[index - 1], report[index])
+        } else {
+            report[index].to_string()
+        };
+        let dampened = match diagnosis.dampened_by {
+            Some(removed) => format!("safe without level {removed} ({})", report[removed]),
+            None => "unsafe with dampener".to_string(),
+        };
+        writeln!(
+            listing,
+            "report {}: {} - {failure} at level {index} ({levels}); {dampened}",
+            number + 1,
+            report.iter().join(" ")
+        )
+        .unwrap();
+    }
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day2::parse_reports;
+    use crate::utils::load_input_for_day;
+
+    #[test]
+    fn test_diagnose() {
+        let policy = SafetyPolicy::default();
+        assert_eq!(
+            diagnose(&[7, 6, 4, 2, 1], &policy),
+            Diagnosis {
+                failure: None,
+                dampened_by: None
+            }
+        );
+        assert_eq!(
+            diagnose(&[1, 2, 7, 8, 9], &policy),
+            Diagnosis {
+                failure: Some((2, Failure::StepTooLarge)),
+                dampened_by: None
+            }
+        );
+        assert_eq!(
+            diagnose(&[1, 3, 2, 4, 5], &policy),
+            Diagnosis {
+                failure: Some((2, Failure::DirectionFlip)),
+                dampened_by: Some(2)
+            }
+        );
+        assert_eq!(
+            diagnose(&[8, 6, 4, 4, 1], &policy),
+            Diagnosis {
+                failure: Some((3, Failure::ZeroStep)),
+                dampened_by: Some(3)
+            }
+        );
+        let bounded = SafetyPolicy {
+            bounds: Some(1..=9),
+            ..SafetyPolicy::default()
+        };
+        assert_eq!(
+            diagnose(&[0, 1, 2], &bounded),
+            Diagnosis {
+                failure: Some((0, Failure::OutOfBounds)),
+                dampened_by: Some(0)
+            }
+        );
+        assert!(diagnose(&[0, 1, 2], &bounded).is_safe(true));
+        assert!(!diagnose(&[0, 1, 2], &bounded).is_safe(false));
+    }
+
+    #[test]
+    fn test_audit() {
+        let reports =
+            parse_reports("7 6 4 2 1\n1 2 7 8 9\n9 7 6 2 1\n1 3 2 4 5\n8 6 4 4 1\n1 3 6 7 9");
+        assert_eq!(
+            audit(&reports, &SafetyPolicy::default()),
+            "report 2: 1 2 7 8 9 - step too large at level 2 (2 -> 7); unsafe with dampener\n\
+             report 3: 9 7 6 2 1 - step too large at level 3 (6 -> 2); unsafe with dampener\n\
+             report 4: 1 3 2 4 5 - direction flip at level 2 (3 -> 2); safe without level 2 (2)\n\
+             report 5: 8 6 4 4 1 - zero step at level 3 (4 -> 4); safe without level 3 (4)\n"
+        );
+    }
+
+    #[test]
+    fn audit_input() {
+        let file_contents = load_input_for_day(2);
+        let reports = parse_reports(file_contents.as_str());
+        let listing = audit(&reports, &SafetyPolicy::default());
+        println!("{listing}");
+        assert_eq!(listing.lines().count(), reports.len() - 524);
+        let rescued = listing
+            .lines()
+            .filter(|line| line.contains("safe without"))
+            .count();
+        assert_eq!(rescued, 569 - 524);
+    }
+}
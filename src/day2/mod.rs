@@ -1,106 +1,588 @@
-fn parse_reports(file_contents: &str) -> Vec<Vec<i32>> {
-    file_contents
-        .split('\n')
-        .map(|line| {
-            line.split_whitespace()
-                .map(|x| x.parse::<i32>().unwrap())
-                .collect()
-        })
-        .collect()
-}
-
-fn is_safe(report: &[i32], dampener: bool) -> bool {
-    let mut previous = report[0];
-    let ascending = report[1] - report[0] > 0;
-    for (current_index, &current) in report[1..].iter().enumerate() {
-        let diff = current - previous;
-        let is_valid = validate_level_change(ascending, diff);
-        if !is_valid {
-            return if dampener {
-                dampener_routine(current_index, report)
+mod diagnostics;
+mod stats;
+
+use std::fmt;
+use std::ops::{Index, RangeInclusive};
+
+/// Every report's levels in one buffer, report `i` is `levels[offsets[i]..offsets[i + 1]]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reports {
+    levels: Vec<i32>,
+    offsets: Vec<usize>,
+}
+
+impl Reports {
+    fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, index: usize) -> Option<&[i32]> {
+        let end = *self.offsets.get(index + 1)?;
+        Some(&self.levels[self.offsets[index]..end])
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &[i32]> + '_ {
+        self.offsets
+            .windows(2)
+            .map(|bounds| &self.levels[bounds[0]..bounds[1]])
+    }
+}
+
+impl Index<usize> for Reports {
+    type Output = [i32];
+
+    fn index(&self, index: usize) -> &[i32] {
+        self.get(index).expect("report index out of range")
+    }
+}
+
+/// Parses one report per line, skipping blank lines.
+fn parse_reports(file_contents: &str) -> Reports {
+    let mut reports = Reports {
+        levels: Vec::new(),
+        offsets: vec![0],
+    };
+    for line in file_contents.lines() {
+        let start = reports.levels.len();
+        reports
+            .levels
+            .extend(line.split_whitespace().map(|x| x.parse::<i32>().unwrap()));
+        if reports.levels.len() > start {
+            reports.offsets.push(reports.levels.len());
+        }
+    }
+    reports
+}
+
+/// Which way a report's levels may move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Directions {
+    Ascending,
+    Descending,
+    Either,
+}
+
+/// The rules a report must follow to be safe, the default is the puzzle's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SafetyPolicy {
+    /// Smallest allowed absolute change between neighbouring levels.
+    min_step: i32,
+    /// Largest allowed absolute change between neighbouring levels.
+    max_step: i32,
+    directions: Directions,
+    /// Whether neighbouring levels may be equal, regardless of `min_step`.
+    allow_equal: bool,
+    /// If set, every level must fall within this range.
+    bounds: Option<RangeInclusive<i32>>,
+}
+
+impl Default for SafetyPolicy {
+    fn default() -> Self {
+        SafetyPolicy {
+            min_step: 1,
+            max_step: 3,
+            directions: Directions::Either,
+            allow_equal: false,
+            bounds: None,
+        }
+    }
+}
+
+impl SafetyPolicy {
+    /// Checks a change between neighbouring levels given the report's direction so far, `None`
+    /// until the first non-zero change. Changes are `i64` as the difference between two `i32`
+    /// levels may not fit in an `i32`, see `step`.
+    fn check_change(&self, ascending: Option<bool>, diff: i64) -> Result<(), Failure> {
+        if diff == 0 {
+            return if self.allow_equal {
+                Ok(())
             } else {
-                false
+                Err(Failure::ZeroStep)
             };
         }
-        previous = current;
+        let rising = diff > 0;
+        let direction_allowed = match self.directions {
+            Directions::Ascending => rising,
+            Directions::Descending => !rising,
+            Directions::Either => true,
+        };
+        if !direction_allowed {
+            Err(Failure::WrongDirection)
+        } else if ascending.is_some_and(|ascending| ascending != rising) {
+            Err(Failure::DirectionFlip)
+        } else if diff.abs() < i64::from(self.min_step) {
+            Err(Failure::StepTooSmall)
+        } else if diff.abs() > i64::from(self.max_step) {
+            Err(Failure::StepTooLarge)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn allows_change(&self, ascending: Option<bool>, diff: i64) -> bool {
+        self.check_change(ascending, diff).is_ok()
+    }
+
+    fn allows_level(&self, level: i32) -> bool {
+        self.bounds
+            .as_ref()
+            .is_none_or(|bounds| bounds.contains(&level))
+    }
+}
+
+/// Why a level broke the safety policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Failure {
+    /// The level equals the one before it.
+    ZeroStep,
+    /// The level moves against the direction set by the earlier levels.
+    DirectionFlip,
+    /// The level moves in a direction the policy doesn't allow.
+    WrongDirection,
+    StepTooSmall,
+    StepTooLarge,
+    /// The level is outside the policy's bounds.
+    OutOfBounds,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Failure::ZeroStep => "zero step",
+            Failure::DirectionFlip => "direction flip",
+            Failure::WrongDirection => "wrong direction",
+            Failure::StepTooSmall => "step too small",
+            Failure::StepTooLarge => "step too large",
+            Failure::OutOfBounds => "out of bounds",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// The change from one level to the next, which can be up to twice as wide as `i32`.
+fn step(from: i32, to: i32) -> i64 {
+    i64::from(to) - i64::from(from)
+}
+
+/// Finds the first level that breaks the policy, along with its index.
+fn first_failure(report: &[i32], policy: &SafetyPolicy) -> Option<(usize, Failure)> {
+    first_failure_in(report.iter().copied(), policy)
+}
+
+fn first_failure_in(
+    levels: impl IntoIterator<Item = i32>,
+    policy: &SafetyPolicy,
+) -> Option<(usize, Failure)> {
+    let mut previous = None;
+    let mut ascending = None;
+    for (index, current) in levels.into_iter().enumerate() {
+        if let Some(previous) = previous {
+            let diff = step(previous, current);
+            if let Err(failure) = policy.check_change(ascending, diff) {
+                return Some((index, failure));
+            }
+            if diff != 0 {
+                ascending = Some(diff > 0);
+            }
+        }
+        if !policy.allows_level(current) {
+            return Some((index, Failure::OutOfBounds));
+        }
+        previous = Some(current);
     }
-    true
+    None
 }
 
-fn dampener_routine(current_index: usize, report: &[i32]) -> bool {
-    let mut right_report = report.to_owned();
-    right_report.remove(current_index + 1); // we start from index 1
-    let mut middle_report = report.to_owned();
-    middle_report.remove(current_index);
-    let mut alternative_reports = vec![right_report, middle_report];
-    if current_index >= 1 {
-        let mut left_report = report.to_owned();
-        left_report.remove(current_index - 1);
-        alternative_reports.push(left_report);
+/// Checks the report is safe, with the dampener as long as removing a single level makes it so.
+/// Empty and single-level reports are safe unless a level is out of bounds.
+fn is_safe(report: &[i32], dampener: bool, policy: &SafetyPolicy) -> bool {
+    match first_failure(report, policy) {
+        None => true,
+        Some((failed_index, _)) => dampener && dampener_routine(failed_index, report, policy),
     }
-    alternative_reports
-        .iter()
-        .any(|report| is_safe(report, false))
 }
 
-fn validate_level_change(ascending: bool, diff: i32) -> bool {
-    if ascending && diff <= 0 {
-        return false;
+/// Removing a level after the first failure leaves the failing prefix in place, so only levels
+/// up to and including it can help. Nearer levels are tried first as they usually do.
+fn dampener_routine(failed_index: usize, report: &[i32], policy: &SafetyPolicy) -> bool {
+    (0..=failed_index).rev().any(|removed| {
+        let levels = report
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| index != removed)
+            .map(|(_, &level)| level);
+        first_failure_in(levels, policy).is_none()
+    })
+}
+
+/// Finds a smallest set of levels to remove, at most `tolerance`, that makes the report safe.
+/// Returns the removed indices in ascending order, or `None` if more removals are needed.
+fn is_safe_with_tolerance(
+    report: &[i32],
+    tolerance: usize,
+    policy: &SafetyPolicy,
+) -> Option<Vec<usize>> {
+    // best[i][d] is the fewest removals among report[..=i] that leave a safe sequence ending
+    // with report[i] kept, where d is its direction: 0 unknown, 1 ascending, 2 descending.
+    let mut best = vec![[None::<(usize, Option<(usize, usize)>)>; 3]; report.len()];
+    for (i, &current) in report.iter().enumerate() {
+        if !policy.allows_level(current) {
+            continue;
+        }
+        best[i][0] = Some((i, None));
+        for j in 0..i {
+            let diff = step(report[j], current);
+            for d in 0..3 {
+                let Some((removals, _)) = best[j][d] else {
+                    continue;
+                };
+                let ascending = match d {
+                    0 => None,
+                    d => Some(d == 1),
+                };
+                if !policy.allows_change(ascending, diff) {
+                    continue;
+                }
+                let removals = removals + i - j - 1;
+                let direction = match diff {
+                    0 => d,
+                    diff if diff > 0 => 1,
+                    _ => 2,
+                };
+                if best[i][direction].is_none_or(|(existing, _)| removals < existing) {
+                    best[i][direction] = Some((removals, Some((j, d))));
+                }
+            }
+        }
     }
-    if !ascending && diff >= 0 {
-        return false;
+    // Removing every level always leaves a safe, empty, report.
+    let best_end = (0..report.len())
+        .flat_map(|i| (0..3).map(move |d| (i, d)))
+        .filter_map(|(i, d)| {
+            best[i][d].map(|(removals, _)| (removals + report.len() - 1 - i, Some((i, d))))
+        })
+        .min()
+        .unwrap_or((report.len(), None));
+    let (removals, mut position) = best_end;
+    if removals > tolerance {
+        return None;
     }
-    if diff.abs() > 3 || diff.abs() < 1 {
-        return false;
+    let mut kept = vec![false; report.len()];
+    while let Some((i, d)) = position {
+        kept[i] = true;
+        position = best[i][d].and_then(|(_, previous)| previous);
     }
-    true
+    Some((0..report.len()).filter(|&i| !kept[i]).collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::load_input_for_day;
+    use crate::utils::{bench, load_input_for_day, SplitMix64};
 
     #[test]
     fn test_parse_reports() {
-        let out = parse_reports(&"1 2 3\n4 5 6");
+        let out = parse_reports("1 2 3\n4 5 6");
         assert_eq!(out[0], vec![1, 2, 3]);
         assert_eq!(out[1], vec![4, 5, 6]);
     }
 
+    #[test]
+    fn test_parse_reports_blank_lines() {
+        let out = parse_reports("\n1 2 3\r\n  \n4 5\n\n");
+        assert_eq!(out.len(), 2);
+        assert_eq!(
+            out.iter().collect::<Vec<_>>(),
+            vec![&[1, 2, 3][..], &[4, 5]]
+        );
+        assert_eq!(out.get(1), Some(&[4, 5][..]));
+        assert_eq!(out.get(2), None);
+        assert!(parse_reports("").is_empty());
+        assert!(parse_reports("\n \n").is_empty());
+    }
+
     #[test]
     fn test_is_safe() {
-        assert!(is_safe(&vec![7, 6, 4, 2, 1], false));
-        assert!(!is_safe(&vec![1, 2, 7, 8, 9], false));
-        assert!(!is_safe(&vec![9, 7, 6, 2, 1], false));
-        assert!(!is_safe(&vec![1, 3, 2, 4, 5], false));
-        assert!(!is_safe(&vec![8, 6, 4, 4, 1], false));
-        assert!(is_safe(&vec![1, 3, 6, 7, 9], true));
+        let policy = SafetyPolicy::default();
+        assert!(is_safe(&[7, 6, 4, 2, 1], false, &policy));
+        assert!(!is_safe(&[1, 2, 7, 8, 9], false, &policy));
+        assert!(!is_safe(&[9, 7, 6, 2, 1], false, &policy));
+        assert!(!is_safe(&[1, 3, 2, 4, 5], false, &policy));
+        assert!(!is_safe(&[8, 6, 4, 4, 1], false, &policy));
+        assert!(is_safe(&[1, 3, 6, 7, 9], true, &policy));
     }
 
     #[test]
     fn test_is_safe_dampened() {
+        let policy = SafetyPolicy::default();
         // aoc test cases
-        assert!(is_safe(&vec![7, 6, 4, 2, 1], true));
-        assert!(!is_safe(&vec![1, 2, 7, 8, 9], true));
-        assert!(!is_safe(&vec![9, 7, 6, 2, 1], true));
-        assert!(is_safe(&vec![1, 3, 2, 4, 5], true));
-        assert!(is_safe(&vec![8, 6, 4, 4, 1], true));
-        assert!(is_safe(&vec![1, 3, 6, 7, 9], true));
+        assert!(is_safe(&[7, 6, 4, 2, 1], true, &policy));
+        assert!(!is_safe(&[1, 2, 7, 8, 9], true, &policy));
+        assert!(!is_safe(&[9, 7, 6, 2, 1], true, &policy));
+        assert!(is_safe(&[1, 3, 2, 4, 5], true, &policy));
+        assert!(is_safe(&[8, 6, 4, 4, 1], true, &policy));
+        assert!(is_safe(&[1, 3, 6, 7, 9], true, &policy));
         // custom
-        assert!(is_safe(&vec![5, 1, 2, 3, 4], true));
-        assert!(is_safe(&vec![1, 1, 2, 3, 4], true));
-        assert!(!is_safe(&vec![1, 1, 1, 3, 4], true));
-        assert!(is_safe(&vec![2, 1, 2, 3, 4], true));
+        assert!(is_safe(&[5, 1, 2, 3, 4], true, &policy));
+        assert!(is_safe(&[1, 1, 2, 3, 4], true, &policy));
+        assert!(!is_safe(&[1, 1, 1, 3, 4], true, &policy));
+        assert!(is_safe(&[2, 1, 2, 3, 4], true, &policy));
+    }
+
+    #[test]
+    fn test_is_safe_with_tolerance() {
+        let policy = SafetyPolicy::default();
+        assert_eq!(
+            is_safe_with_tolerance(&[7, 6, 4, 2, 1], 0, &policy),
+            Some(vec![])
+        );
+        assert_eq!(is_safe_with_tolerance(&[1, 2, 7, 8, 9], 1, &policy), None);
+        assert_eq!(is_safe_with_tolerance(&[1, 3, 2, 4, 5], 0, &policy), None);
+        assert_eq!(
+            is_safe_with_tolerance(&[1, 3, 2, 4, 5], 1, &policy),
+            Some(vec![2])
+        );
+        assert_eq!(
+            is_safe_with_tolerance(&[8, 6, 4, 4, 1], 1, &policy),
+            Some(vec![3])
+        );
+        assert_eq!(
+            is_safe_with_tolerance(&[5, 1, 2, 3, 4], 1, &policy),
+            Some(vec![0])
+        );
+        assert_eq!(is_safe_with_tolerance(&[1, 1, 1, 3, 4], 1, &policy), None);
+        assert_eq!(
+            is_safe_with_tolerance(&[1, 1, 1, 3, 4], 2, &policy),
+            Some(vec![1, 2])
+        );
+        assert_eq!(
+            is_safe_with_tolerance(&[1, 9, 2, 9, 3, 4], 2, &policy),
+            Some(vec![1, 3])
+        );
+        assert_eq!(
+            is_safe_with_tolerance(&[9, 1, 2, 3, 9, 9], 3, &policy),
+            Some(vec![0, 4, 5])
+        );
+        assert_eq!(
+            is_safe_with_tolerance(&[1, 2, 7, 8, 9], 2, &policy),
+            Some(vec![0, 1])
+        );
+        assert_eq!(is_safe_with_tolerance(&[4], 0, &policy), Some(vec![]));
+        assert_eq!(is_safe_with_tolerance(&[], 0, &policy), Some(vec![]));
+    }
+
+    #[test]
+    fn test_is_safe_short_reports() {
+        let policy = SafetyPolicy::default();
+        assert!(is_safe(&[], false, &policy));
+        assert!(is_safe(&[5], false, &policy));
+        assert!(is_safe(&[5, 5], true, &policy));
+        assert!(!is_safe(&[5, 5], false, &policy));
+        let bounded = SafetyPolicy {
+            bounds: Some(1..=9),
+            ..SafetyPolicy::default()
+        };
+        assert!(!is_safe(&[10], false, &bounded));
+        assert!(is_safe(&[10], true, &bounded));
+    }
+
+    #[test]
+    fn test_is_safe_dampened_direction() {
+        let policy = SafetyPolicy::default();
+        // the first step sets the wrong direction
+        assert!(is_safe(&[5, 6, 4, 3, 2], true, &policy));
+        assert!(is_safe(&[3, 2, 4, 5, 6], true, &policy));
+        // the direction was set well before the failure
+        let policy = SafetyPolicy {
+            allow_equal: true,
+            ..SafetyPolicy::default()
+        };
+        assert!(is_safe(&[1, 2, 2, 2, 1], true, &policy));
+    }
+
+    /// Dampened safety, by definition: the report or some copy with one level removed is safe.
+    fn is_safe_brute_force(report: &[i32], policy: &SafetyPolicy) -> bool {
+        is_safe(report, false, policy)
+            || (0..report.len()).any(|removed| {
+                let mut shorter = report.to_vec();
+                shorter.remove(removed);
+                is_safe(&shorter, false, policy)
+            })
+    }
+
+    #[test]
+    fn test_is_safe_dampened_matches_brute_force() {
+        let mut rng = SplitMix64::new(2);
+        for _ in 0..50_000 {
+            let policy = SafetyPolicy {
+                min_step: rng.below(2) as i32,
+                max_step: 1 + rng.below(4) as i32,
+                directions: [
+                    Directions::Ascending,
+                    Directions::Descending,
+                    Directions::Either,
+                ][rng.below(3)],
+                allow_equal: rng.chance(0.3),
+                bounds: rng.chance(0.3).then_some(1..=8),
+            };
+            // levels near the ends of `i32` too, where naive steps overflow
+            let base = [0, i32::MIN + 40, i32::MAX - 40][rng.below(3)];
+            let mut report = vec![base + rng.below(10) as i32];
+            for _ in 0..rng.below(8) {
+                if rng.chance(0.1) {
+                    report.push([i32::MIN, -2, i32::MAX][rng.below(3)]);
+                } else {
+                    let step = rng.below(9) as i32 - 4;
+                    report.push(report[report.len() - 1].saturating_add(step));
+                }
+            }
+            report.truncate(rng.below(report.len() + 1));
+            let expected = is_safe_brute_force(&report, &policy);
+            assert_eq!(
+                is_safe(&report, true, &policy),
+                expected,
+                "{report:?} {policy:?}"
+            );
+            assert_eq!(
+                is_safe_with_tolerance(&report, 1, &policy).is_some(),
+                expected,
+                "{report:?} {policy:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_extreme_levels() {
+        let policy = SafetyPolicy::default();
+        assert_eq!(
+            first_failure(&[-2, i32::MAX], &policy),
+            Some((1, Failure::StepTooLarge))
+        );
+        assert!(is_safe(&[i32::MIN, i32::MAX, i32::MIN + 1], true, &policy));
+        assert_eq!(
+            is_safe_with_tolerance(&[i32::MAX, i32::MIN, i32::MAX - 1], 1, &policy),
+            Some(vec![1])
+        );
+    }
+
+    #[test]
+    fn test_safety_policy() {
+        let policy = SafetyPolicy {
+            max_step: 5,
+            ..SafetyPolicy::default()
+        };
+        assert!(is_safe(&[1, 2, 7, 8, 9], false, &policy));
+        assert!(!is_safe(&[1, 2, 8, 9], false, &policy));
+        let policy = SafetyPolicy {
+            directions: Directions::Descending,
+            ..SafetyPolicy::default()
+        };
+        assert!(is_safe(&[7, 6, 4, 2, 1], false, &policy));
+        assert!(!is_safe(&[1, 3, 6, 7, 9], false, &policy));
+        assert!(is_safe(&[1, 9, 8, 7], true, &policy));
+        let policy = SafetyPolicy {
+            allow_equal: true,
+            ..SafetyPolicy::default()
+        };
+        assert!(is_safe(&[8, 8, 6, 4, 4, 1], false, &policy));
+        assert!(is_safe(&[1, 1, 2, 3, 4], false, &policy));
+        assert!(!is_safe(&[1, 1, 2, 1], false, &policy));
+        assert_eq!(
+            is_safe_with_tolerance(&[3, 3, 9, 2, 2, 1], 1, &policy),
+            Some(vec![2])
+        );
+        let policy = SafetyPolicy {
+            bounds: Some(1..=9),
+            ..SafetyPolicy::default()
+        };
+        assert!(!is_safe(&[0, 1, 2], false, &policy));
+        assert!(is_safe(&[0, 1, 2], true, &policy));
+        assert!(is_safe(&[1, 2, 10], true, &policy));
+        assert!(!is_safe(&[0, 1, 10], true, &policy));
+        assert_eq!(
+            is_safe_with_tolerance(&[0, 1, 10], 2, &policy),
+            Some(vec![0, 2])
+        );
+        assert_eq!(is_safe_with_tolerance(&[10], 1, &policy), Some(vec![0]));
+        assert_eq!(is_safe_with_tolerance(&[10], 0, &policy), None);
+    }
+
+    #[test]
+    fn test_safety_policy_what_if() {
+        let file_contents = load_input_for_day(2);
+        let reports = parse_reports(file_contents.as_str());
+        let count_safe = |policy: &SafetyPolicy| {
+            reports
+                .iter()
+                .filter(|report| is_safe(report, false, policy))
+                .count()
+        };
+        assert_eq!(count_safe(&SafetyPolicy::default()), 524);
+        let wider = SafetyPolicy {
+            max_step: 4,
+            ..SafetyPolicy::default()
+        };
+        assert_eq!(count_safe(&wider), 552);
+    }
+
+    /// One `Vec` per line, to compare against the flat `Reports` layout.
+    fn parse_reports_nested(file_contents: &str) -> Vec<Vec<i32>> {
+        file_contents
+            .split('\n')
+            .map(|line| {
+                line.split_whitespace()
+                    .map(|x| x.parse::<i32>().unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reports_match_nested() {
+        let file_contents = load_input_for_day(2);
+        let nested = parse_reports_nested(file_contents.as_str());
+        let reports = parse_reports(file_contents.as_str());
+        assert_eq!(reports.len(), nested.len());
+        assert!(reports.iter().eq(nested.iter().map(Vec::as_slice)));
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_report_layouts() {
+        let file_contents = load_input_for_day(2);
+        let policy = SafetyPolicy::default();
+        bench("parse nested", 1000, || {
+            parse_reports_nested(file_contents.as_str())
+        });
+        bench("parse flat", 1000, || parse_reports(file_contents.as_str()));
+        let nested = parse_reports_nested(file_contents.as_str());
+        let reports = parse_reports(file_contents.as_str());
+        bench("dampened nested", 1000, || {
+            nested
+                .iter()
+                .filter(|report| is_safe(report, true, &policy))
+                .count()
+        });
+        bench("dampened flat", 1000, || {
+            reports
+                .iter()
+                .filter(|report| is_safe(report, true, &policy))
+                .count()
+        });
     }
 
     #[test]
     fn calculate_pt_1() {
+        let policy = SafetyPolicy::default();
         let file_contents = load_input_for_day(2);
         let reports = parse_reports(file_contents.as_str());
         let total_safe: usize = reports
-            .into_iter()
-            .map(|report| is_safe(&report, false) as usize)
+            .iter()
+            .map(|report| is_safe(report, false, &policy) as usize)
             .sum();
         println!("total safe reports is {total_safe:?}");
         assert_eq!(total_safe, 524)
@@ -108,13 +590,19 @@ mod tests {
 
     #[test]
     fn calculate_pt_2() {
+        let policy = SafetyPolicy::default();
         let file_contents = load_input_for_day(2);
         let reports = parse_reports(file_contents.as_str());
         let total_safe: usize = reports
-            .into_iter()
-            .map(|report| is_safe(&report, true) as usize)
+            .iter()
+            .map(|report| is_safe(report, true, &policy) as usize)
             .sum();
         println!("total safe reports is {total_safe:?}");
-        assert_eq!(total_safe, 569)
+        assert_eq!(total_safe, 569);
+        let tolerated = parse_reports(file_contents.as_str())
+            .iter()
+            .filter(|report| is_safe_with_tolerance(report, 1, &policy).is_some())
+            .count();
+        assert_eq!(tolerated, 569)
     }
 }
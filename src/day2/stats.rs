@@ -0,0 +1,158 @@
+use super::diagnostics::diagnose;
+use super::{step, Failure, Reports, SafetyPolicy};
+use crate::utils::json_object;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write};
+
+/// Longest bar drawn in a text histogram.
+const BAR_WIDTH: usize = 40;
+
+/// Distributions over a set of reports, for sanity checking an input.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ReportStats {
+    pub reports: usize,
+    /// Reports that are safe without the dampener.
+    pub safe: usize,
+    /// Number of reports of each length.
+    pub lengths: BTreeMap<usize, usize>,
+    /// Number of times each signed step between neighbouring levels occurs.
+    pub steps: BTreeMap<i64, usize>,
+    /// Why each unsafe report first fails.
+    pub failures: BTreeMap<Failure, usize>,
+    /// Number of unsafe reports the dampener rescues by removing the level at each position.
+    pub rescues: BTreeMap<usize, usize>,
+}
+
+impl ReportStats {
+    pub fn new(reports: &Reports, policy: &SafetyPolicy) -> Self {
+        let mut stats = ReportStats {
+            reports: reports.len(),
+            ..ReportStats::default()
+        };
+        for report in reports.iter() {
+            *stats.lengths.entry(report.len()).or_default() += 1;
+            for pair in report.windows(2) {
+                *stats.steps.entry(step(pair[0], pair[1])).or_default() += 1;
+            }
+            let diagnosis = diagnose(report, policy);
+            match diagnosis.failure {
+                None => stats.safe += 1,
+                Some((_, failure)) => *stats.failures.entry(failure).or_default() += 1,
+            }
+            if let Some(removed) = diagnosis.dampened_by {
+                *stats.rescues.entry(removed).or_default() += 1;
+            }
+        }
+        stats
+    }
+
+    /// Reports that are safe with the dampener.
+    pub fn dampened_safe(&self) -> usize {
+        self.safe + self.rescues.values().sum::<usize>()
+    }
+
+    pub fn histograms(&self) -> String {
+        format!(
+            "{} reports, {} safe, {} safe with the dampener\n\n{}\n{}\n{}\n{}",
+            self.reports,
+            self.safe,
+            self.dampened_safe(),
+            histogram("report lengths", &self.lengths),
+            histogram("steps", &self.steps),
+            histogram("failures", &self.failures),
+            histogram("rescued by removing level", &self.rescues)
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        json_object([
+            ("reports", self.reports.to_string()),
+            ("safe", self.safe.to_string()),
+            ("dampened_safe", self.dampened_safe().to_string()),
+            ("lengths", json_object(&self.lengths)),
+            ("steps", json_object(&self.steps)),
+            ("failures", json_object(&self.failures)),
+            ("rescues", json_object(&self.rescues)),
+        ])
+    }
+}
+
+/// Draws a bar per key, scaled so the largest count fills `BAR_WIDTH`.
+fn histogram<K: Display>(title: &str, counts: &BTreeMap<K, usize>) -> String {
+    let mut text = format!("{title}\n");
+    let labels: Vec<String> = counts.keys().map(|key| key.to_string()).collect();
+    let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+    let max = counts.values().copied().max().unwrap_or(0);
+    for (label, &count) in labels.iter().zip(counts.values()) {
+        let bar = "#".repeat((count * BAR_WIDTH).div_ceil(max));
+        writeln!(text, "{label:>label_width$} | {bar} {count}").unwrap();
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day2::parse_reports;
+    use crate::utils::load_input_for_day;
+
+    fn example() -> ReportStats {
+        let reports =
+            parse_reports("7 6 4 2 1\n1 2 7 8 9\n9 7 6 2 1\n1 3 2 4 5\n8 6 4 4 1\n1 3 6 7 9");
+        ReportStats::new(&reports, &SafetyPolicy::default())
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = example();
+        assert_eq!(stats.reports, 6);
+        assert_eq!(stats.safe, 2);
+        assert_eq!(stats.dampened_safe(), 4);
+        assert_eq!(stats.lengths, BTreeMap::from([(5, 6)]));
+        assert_eq!(stats.steps.values().sum::<usize>(), 24);
+        assert_eq!(stats.steps[&-2], 5);
+        assert_eq!(
+            stats.failures,
+            BTreeMap::from([
+                (Failure::ZeroStep, 1),
+                (Failure::DirectionFlip, 1),
+                (Failure::StepTooLarge, 2)
+            ])
+        );
+        assert_eq!(stats.rescues, BTreeMap::from([(2, 1), (3, 1)]));
+    }
+
+    #[test]
+    fn test_histograms() {
+        let text = example().histograms();
+        assert!(text.starts_with("6 reports, 2 safe, 4 safe with the dampener\n\n"));
+        assert!(text.contains(&format!(
+            "report lengths\n5 | {} 6\n",
+            "#".repeat(BAR_WIDTH)
+        )));
+        assert!(text.contains(&format!(
+            "failures\n     zero step | {half} 1\ndirection flip | {half} 1\nstep too large | {full} 2\n",
+            half = "#".repeat(BAR_WIDTH / 2),
+            full = "#".repeat(BAR_WIDTH)
+        )));
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            example().to_json(),
+            r#"{"reports":6,"safe":2,"dampened_safe":4,"lengths":{"5":6},"steps":{"-4":1,"-3":1,"-2":5,"-1":5,"0":1,"1":5,"2":4,"3":1,"5":1},"failures":{"zero step":1,"direction flip":1,"step too large":2},"rescues":{"2":1,"3":1}}"#
+        );
+    }
+
+    #[test]
+    fn stats_input() {
+        let file_contents = load_input_for_day(2);
+        let reports = parse_reports(file_contents.as_str());
+        let stats = ReportStats::new(&reports, &SafetyPolicy::default());
+        println!("{}", stats.histograms());
+        assert_eq!(stats.safe, 524);
+        assert_eq!(stats.dampened_safe(), 569);
+        assert_eq!(stats.lengths.values().sum::<usize>(), reports.len());
+    }
+}
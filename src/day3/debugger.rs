@@ -0,0 +1,287 @@
+use super::{InstructionSet, Operation, OverflowError, State};
+use crate::utils::{json_array, json_object, json_string};
+use std::fmt::Write;
+
+/// What happened when a single operation ran.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TraceEntry {
+    /// Byte offset of the operation in the input.
+    pub offset: usize,
+    pub operation: Operation,
+    /// Whether multiplication was enabled when the operation ran.
+    pub enabled: bool,
+    /// The product for a `mul`, only added to the total when enabled. Wide enough that it
+    /// can't overflow, as a disabled `mul` doesn't have to fit in the sum.
+    pub product: Option<u128>,
+    /// The sum once the operation has run.
+    pub total: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trace(pub Vec<TraceEntry>);
+
+impl Trace {
+    pub fn total(&self) -> usize {
+        self.0.last().map_or(0, |entry| entry.total)
+    }
+
+    /// The sum ignoring `do()` and `don't()`, i.e. the part 1 answer, `None` on overflow.
+    pub fn unconditional_total(&self) -> Option<usize> {
+        self.0
+            .iter()
+            .filter_map(|entry| entry.product)
+            .try_fold(0usize, |sum, product| {
+                sum.checked_add(usize::try_from(product).ok()?)
+            })
+    }
+
+    /// Lists the entries, showing operations as `set` names them.
+    pub fn table(&self, set: &InstructionSet) -> String {
+        let mut table = format!(
+            "{:>8}  {:<16}  {:<7}  {:>10}  {:>12}\n",
+            "offset", "operation", "enabled", "product", "total"
+        );
+        for entry in &self.0 {
+            let product = entry.product.map_or(String::new(), |p| p.to_string());
+            writeln!(
+                table,
+                "{:>8}  {:<16}  {:<7}  {:>10}  {:>12}",
+                entry.offset,
+                set.render(&entry.operation),
+                if entry.enabled { "yes" } else { "no" },
+                product,
+                entry.total
+            )
+            .unwrap();
+        }
+        table
+    }
+
+    pub fn to_json(&self, set: &InstructionSet) -> String {
+        json_array(self.0.iter().map(|entry| {
+            json_object([
+                ("offset", entry.offset.to_string()),
+                ("operation", json_string(&set.render(&entry.operation))),
+                ("enabled", entry.enabled.to_string()),
+                (
+                    "product",
+                    entry.product.map_or("null".to_string(), |p| p.to_string()),
+                ),
+                ("total", entry.total.to_string()),
+            ])
+        }))
+    }
+}
+
+/// Steps through a program one operation at a time.
+pub struct Debugger<'a> {
+    set: &'a InstructionSet,
+    program: Vec<(usize, Operation)>,
+    next: usize,
+    state: State,
+}
+
+impl<'a> Debugger<'a> {
+    pub fn new(set: &'a InstructionSet, input: &str) -> Self {
+        Self {
+            set,
+            program: set.locate(input),
+            next: 0,
+            state: State::default(),
+        }
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// The operation `step` will run next, with its byte offset.
+    pub fn peek(&self) -> Option<&(usize, Operation)> {
+        self.program.get(self.next)
+    }
+
+    /// Runs the next operation, `None` once the program has finished. An overflow leaves the
+    /// state as it was, so stepping again retries the same operation.
+    pub fn step(&mut self) -> Option<Result<TraceEntry, OverflowError>> {
+        let (offset, operation) = self.program.get(self.next)?.clone();
+        let enabled = self.state.enabled;
+        let mut state = self.state.clone();
+        if let Err(e) = self.set.execute(&operation, &mut state) {
+            return Some(Err(e));
+        }
+        self.state = state;
+        self.next += 1;
+        let product = match operation {
+            Operation::Mul(l, r) => Some(l as u128 * r as u128),
+            _ => None,
+        };
+        Some(Ok(TraceEntry {
+            offset,
+            operation,
+            enabled,
+            product,
+            total: self.state.sum,
+        }))
+    }
+
+    /// Runs the rest of the program, stopping at the first overflow.
+    pub fn run(&mut self) -> Result<Trace, OverflowError> {
+        std::iter::from_fn(|| self.step())
+            .collect::<Result<Vec<TraceEntry>, OverflowError>>()
+            .map(Trace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day3::{DontExtractor, Instruction, OperationExtractor, STANDARD};
+    use crate::utils::load_input_for_day;
+
+    const EXAMPLE: &str =
+        "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+
+    #[test]
+    fn test_step() {
+        let mut debugger = Debugger::new(&STANDARD, EXAMPLE);
+        assert_eq!(debugger.peek(), Some(&(1, Operation::Mul(2, 4))));
+        assert_eq!(
+            debugger.step(),
+            Some(Ok(TraceEntry {
+                offset: 1,
+                operation: Operation::Mul(2, 4),
+                enabled: true,
+                product: Some(8),
+                total: 8
+            }))
+        );
+        debugger.step();
+        assert!(!debugger.state().enabled);
+        assert_eq!(
+            debugger.step(),
+            Some(Ok(TraceEntry {
+                offset: 28,
+                operation: Operation::Mul(5, 5),
+                enabled: false,
+                product: Some(25),
+                total: 8
+            }))
+        );
+        assert_eq!(debugger.run().unwrap().0.len(), 3);
+        assert_eq!(debugger.step(), None);
+        assert_eq!(debugger.state().sum, 48);
+    }
+
+    #[test]
+    fn test_trace_views() {
+        let trace = Debugger::new(&STANDARD, "mul(2,4)don't()mul(3,3)")
+            .run()
+            .unwrap();
+        assert_eq!(trace.total(), 8);
+        assert_eq!(trace.unconditional_total(), Some(17));
+        assert_eq!(
+            trace.table(&STANDARD),
+            concat!(
+                "  offset  operation         enabled     product         total\n",
+                "       0  mul(2,4)          yes               8             8\n",
+                "       8  don't()           yes                             8\n",
+                "      15  mul(3,3)          no                9             8\n",
+            )
+        );
+        assert_eq!(
+            trace.to_json(&STANDARD),
+            concat!(
+                r#"[{"offset":0,"operation":"mul(2,4)","enabled":true,"product":8,"total":8},"#,
+                r#"{"offset":8,"operation":"don't()","enabled":true,"product":null,"total":8},"#,
+                r#"{"offset":15,"operation":"mul(3,3)","enabled":false,"product":9,"total":8}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_trace_custom_names() {
+        let mut set = InstructionSet::standard();
+        set.register(Instruction::new(r#"say"hi\"#, 1, |_, _| Some(())))
+            .unwrap();
+        let trace = Debugger::new(&set, r#"say"hi\(7)mul(1,2)"#).run().unwrap();
+        assert_eq!(
+            trace.table(&set).lines().nth(1),
+            Some(r#"       0  say"hi\(7)        yes                             0"#)
+        );
+        assert_eq!(
+            trace.to_json(&set),
+            concat!(
+                r#"[{"offset":0,"operation":"say\"hi\\(7)","enabled":true,"product":null,"total":0},"#,
+                r#"{"offset":10,"operation":"mul(1,2)","enabled":true,"product":2,"total":2}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_trace_input() {
+        let file_contents = load_input_for_day(3);
+        let trace = Debugger::new(&STANDARD, &file_contents).run().unwrap();
+        assert_eq!(trace.total(), 77055967);
+        assert_eq!(trace.unconditional_total(), Some(153469856));
+    }
+
+    #[test]
+    fn test_disabled_product_overflow() {
+        /// Accepts `mul(a,b)` with operands as wide as a `usize`.
+        struct WideMultiplyExtractor;
+
+        impl OperationExtractor for WideMultiplyExtractor {
+            fn pattern(&self) -> &str {
+                r"mul\([0-9]{1,19},[0-9]{1,19}\)"
+            }
+
+            fn extract(&self, s: &str) -> Operation {
+                let (left, right) = s[4..s.len() - 1].split_once(',').unwrap();
+                Operation::Mul(left.parse().unwrap(), right.parse().unwrap())
+            }
+        }
+
+        let mut set = InstructionSet::empty();
+        set.register_extractor(WideMultiplyExtractor)
+            .unwrap()
+            .register_extractor(DontExtractor)
+            .unwrap();
+        let big = usize::MAX / 2 + 1;
+        let input = format!("mul(2,3)don't()mul({big},4)");
+        let mut debugger = Debugger::new(&set, &input);
+        debugger.step();
+        debugger.step();
+        assert_eq!(
+            debugger.step(),
+            Some(Ok(TraceEntry {
+                offset: 15,
+                operation: Operation::Mul(big, 4),
+                enabled: false,
+                product: Some(big as u128 * 4),
+                total: 6
+            }))
+        );
+        let trace = Debugger::new(&set, &input).run().unwrap();
+        assert_eq!(trace.total(), 6);
+        assert_eq!(trace.unconditional_total(), None);
+    }
+
+    #[test]
+    fn test_step_overflow() {
+        let mut set = InstructionSet::standard();
+        set.register(Instruction::new("max", 0, |_, state| {
+            state.sum = usize::MAX;
+            Some(())
+        }))
+        .unwrap();
+        let mut debugger = Debugger::new(&set, "max()mul(1,1)do()");
+        assert!(debugger.step().unwrap().is_ok());
+        let overflow = Err(OverflowError {
+            operation: Operation::Mul(1, 1),
+        });
+        assert_eq!(debugger.step(), Some(overflow.clone()));
+        assert_eq!(debugger.peek(), Some(&(5, Operation::Mul(1, 1))));
+        assert_eq!(debugger.run().err(), overflow.err());
+        assert_eq!(debugger.state().sum, usize::MAX);
+    }
+}
@@ -1,16 +1,92 @@
-use regex::Regex;
+mod debugger;
 
-const RE_DO: &str = r"mul\(\d+,\d+\)";
+use num_bigint::BigUint;
+use regex::Regex;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::ops::RangeInclusive;
+use std::sync::LazyLock;
 
-#[derive(Debug, PartialEq, Copy, Clone)]
-enum Operation {
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operation {
     Mul(usize, usize),
     Do,
     DoNot,
+    /// An instruction registered with an `InstructionSet`, `id` is its registration order.
+    Custom {
+        id: usize,
+        args: Vec<usize>,
+    },
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Mul(l, r) => write!(f, "mul({l},{r})"),
+            Operation::Do => write!(f, "do()"),
+            Operation::DoNot => write!(f, "don't()"),
+            Operation::Custom { id, args } => {
+                let args: Vec<String> = args.iter().map(usize::to_string).collect();
+                write!(f, "custom#{id}({})", args.join(","))
+            }
+        }
+    }
+}
+
+/// The sum no longer fits in a `usize`, `InstructionSet::compute_exact` can go further for
+/// programs without custom instructions.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OverflowError {
+    /// The operation that overflowed.
+    pub operation: Operation,
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sum overflowed running {}", self.operation)
+    }
+}
+
+impl Error for OverflowError {}
+
+/// An extractor's pattern, or the alternation it's compiled into, isn't a valid regex.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PatternError {
+    pub pattern: String,
+    pub source: regex::Error,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid pattern {:?}: {}", self.pattern, self.source)
+    }
 }
 
-trait OperationExtractor {
-    fn compile_regex(&self) -> Regex;
+impl Error for PatternError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Running state of a program, instructions read and update it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct State {
+    pub sum: usize,
+    pub enabled: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            sum: 0,
+            enabled: true,
+        }
+    }
+}
+
+pub trait OperationExtractor {
+    fn pattern(&self) -> &str;
 
     fn extract(&self, s: &str) -> Operation;
 }
@@ -18,8 +94,8 @@ trait OperationExtractor {
 pub struct MultiplyExtractor;
 
 impl OperationExtractor for MultiplyExtractor {
-    fn compile_regex(&self) -> Regex {
-        Regex::new(r"mul\(\d+,\d+\)").unwrap()
+    fn pattern(&self) -> &str {
+        r"mul\([0-9]{1,3},[0-9]{1,3}\)"
     }
 
     fn extract(&self, s: &str) -> Operation {
@@ -37,8 +113,8 @@ impl OperationExtractor for MultiplyExtractor {
 pub struct DoExtractor;
 
 impl OperationExtractor for DoExtractor {
-    fn compile_regex(&self) -> Regex {
-        Regex::new(r"do\(\)").unwrap()
+    fn pattern(&self) -> &str {
+        r"do\(\)"
     }
 
     fn extract(&self, _s: &str) -> Operation {
@@ -48,8 +124,8 @@ impl OperationExtractor for DoExtractor {
 pub struct DontExtractor;
 
 impl OperationExtractor for DontExtractor {
-    fn compile_regex(&self) -> Regex {
-        Regex::new(r"don't\(\)").unwrap()
+    fn pattern(&self) -> &str {
+        r"don't\(\)"
     }
 
     fn extract(&self, _s: &str) -> Operation {
@@ -57,56 +133,562 @@ impl OperationExtractor for DontExtractor {
     }
 }
 
-fn extract(input: &str) -> Vec<Operation> {
-    let extractors: [Box<dyn OperationExtractor>; 3] = [
-        Box::new(MultiplyExtractor),
-        Box::new(DoExtractor),
-        Box::new(DontExtractor),
-    ];
-    let mut unsorted_operators: Vec<(usize, Operation)> = extractors
-        .iter()
-        .flat_map(|ext| {
-            ext.compile_regex()
-                .find_iter(input)
-                .map(|m| (m.start(), ext.extract(m.as_str())))
-                .collect::<Vec<(usize, Operation)>>()
-        })
-        .collect();
-    unsorted_operators.sort_by(|(a, _), (b, _)| a.cmp(b));
-    unsorted_operators.into_iter().map(|(_, op)| op).collect()
+type Semantics = Box<dyn Fn(&[usize], &mut State) -> Option<()> + Send + Sync>;
+
+/// A new instruction `name(a,b,...)` taking `arity` numeric arguments, with `semantics` run by
+/// `InstructionSet::compute` whenever it's encountered, enabled or not. Semantics return `None`
+/// when the sum would overflow, and any changes they made to the state are then discarded.
+pub struct Instruction {
+    name: String,
+    arity: usize,
+    semantics: Semantics,
+}
+
+impl Instruction {
+    pub fn new(
+        name: &str,
+        arity: usize,
+        semantics: impl Fn(&[usize], &mut State) -> Option<()> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            semantics: Box::new(semantics),
+        }
+    }
+}
+
+struct CustomExtractor {
+    id: usize,
+    prefix: String,
+    pattern: String,
 }
 
-fn compute(input: &Vec<Operation>) -> usize {
-    let mut sum = 0;
-    let mut enabled = true;
+impl CustomExtractor {
+    fn new(id: usize, instruction: &Instruction) -> Self {
+        // ASCII digits only, `\d` matches any Unicode digit, and any number with one digit fewer
+        // than `usize::MAX` fits, so arguments always parse
+        let arg = format!(r"[0-9]{{1,{}}}", usize::MAX.ilog10());
+        let args = vec![arg.as_str(); instruction.arity].join(",");
+        Self {
+            id,
+            prefix: format!("{}(", instruction.name),
+            pattern: format!(r"{}\({args}\)", regex::escape(&instruction.name)),
+        }
+    }
+}
+
+impl OperationExtractor for CustomExtractor {
+    fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    fn extract(&self, s: &str) -> Operation {
+        let args = s
+            .strip_prefix(&self.prefix)
+            .unwrap()
+            .strip_suffix(")")
+            .unwrap();
+        Operation::Custom {
+            id: self.id,
+            args: args
+                .split(',')
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| arg.parse().unwrap())
+                .collect(),
+        }
+    }
+}
+
+/// The instructions recognised in corrupted memory, extendable with new extractors for the
+/// built-in operations or with entirely new instructions.
+pub struct InstructionSet {
+    extractors: Vec<Box<dyn OperationExtractor + Send + Sync>>,
+    instructions: Vec<Instruction>,
+    /// Every extractor's pattern in one alternation, see `groups`.
+    regex: Regex,
+    /// The capture group index matching each extractor.
+    groups: Vec<usize>,
+}
+
+impl InstructionSet {
+    pub fn empty() -> Self {
+        Self {
+            extractors: Vec::new(),
+            instructions: Vec::new(),
+            regex: Regex::new(r"[^\s\S]").unwrap(),
+            groups: Vec::new(),
+        }
+    }
+
+    /// The puzzle's `mul(a,b)`, `do()` and `don't()`.
+    pub fn standard() -> Self {
+        let mut set = Self::empty();
+        set.register_extractor(MultiplyExtractor)
+            .and_then(|set| set.register_extractor(DoExtractor))
+            .and_then(|set| set.register_extractor(DontExtractor))
+            .expect("built-in patterns are valid");
+        set
+    }
+
+    /// Adds an extractor, leaving the set unchanged if its pattern doesn't compile.
+    pub fn register_extractor(
+        &mut self,
+        extractor: impl OperationExtractor + Send + Sync + 'static,
+    ) -> Result<&mut Self, PatternError> {
+        self.extractors.push(Box::new(extractor));
+        if let Err(e) = self.compile() {
+            self.extractors.pop();
+            return Err(e);
+        }
+        Ok(self)
+    }
+
+    pub fn register(&mut self, instruction: Instruction) -> Result<&mut Self, PatternError> {
+        let extractor = CustomExtractor::new(self.instructions.len(), &instruction);
+        self.instructions.push(instruction);
+        if let Err(e) = self.register_extractor(extractor) {
+            self.instructions.pop();
+            return Err(e);
+        }
+        Ok(self)
+    }
+
+    /// Rebuilds the alternation over every extractor, failing on the newest one's pattern.
+    fn compile(&mut self) -> Result<(), PatternError> {
+        let pattern = (0..self.extractors.len())
+            .map(|idx| format!("(?<e{idx}>{})", self.extractors[idx].pattern()))
+            .collect::<Vec<String>>()
+            .join("|");
+        self.regex = Regex::new(&pattern).map_err(|source| PatternError {
+            pattern: self
+                .extractors
+                .last()
+                .map_or("", |e| e.pattern())
+                .to_string(),
+            source,
+        })?;
+        let names: Vec<Option<&str>> = self.regex.capture_names().collect();
+        self.groups = (0..self.extractors.len())
+            .map(|idx| {
+                let name = format!("e{idx}");
+                names.iter().position(|n| *n == Some(&name)).unwrap()
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Shows an operation as it's written in memory, naming custom instructions as registered.
+    pub fn render(&self, op: &Operation) -> String {
+        match op {
+            Operation::Custom { id, args } if *id < self.instructions.len() => {
+                let args: Vec<String> = args.iter().map(usize::to_string).collect();
+                format!("{}({})", self.instructions[*id].name, args.join(","))
+            }
+            _ => op.to_string(),
+        }
+    }
+
+    /// Extracts the operations in a single pass, in the order they appear.
+    pub fn extract(&self, input: &str) -> Vec<Operation> {
+        self.locate(input).into_iter().map(|(_, op)| op).collect()
+    }
+
+    /// Extracts the operations along with their byte offsets in the input.
+    pub fn locate(&self, input: &str) -> Vec<(usize, Operation)> {
+        self.regex
+            .captures_iter(input)
+            .map(|captures| {
+                let (extractor, m) = self
+                    .groups
+                    .iter()
+                    .enumerate()
+                    .find_map(|(idx, &group)| Some((idx, captures.get(group)?)))
+                    .unwrap();
+                (m.start(), self.extractors[extractor].extract(m.as_str()))
+            })
+            .collect()
+    }
+
+    pub fn compute(&self, input: &[Operation]) -> Result<usize, OverflowError> {
+        let mut state = State::default();
+        for op in input {
+            self.execute(op, &mut state)?;
+        }
+        Ok(state.sum)
+    }
+
+    /// Computes the sum with arbitrary precision so it's always exact. Custom instructions only
+    /// see a `usize` sum, so running one once the sum no longer fits, or having one overflow,
+    /// fails rather than guess at the total.
+    pub fn compute_exact(&self, input: &[Operation]) -> Result<BigUint, OverflowError> {
+        let mut sum = BigUint::ZERO;
+        let mut enabled = true;
+        for op in input {
+            match op {
+                Operation::Mul(l, r) => {
+                    if enabled {
+                        sum += *l as u128 * *r as u128;
+                    }
+                }
+                Operation::Do => enabled = true,
+                Operation::DoNot => enabled = false,
+                Operation::Custom { .. } => {
+                    let overflow = || OverflowError {
+                        operation: op.clone(),
+                    };
+                    let mut state = State {
+                        sum: usize::try_from(&sum).map_err(|_| overflow())?,
+                        enabled,
+                    };
+                    self.execute(op, &mut state)?;
+                    sum = BigUint::from(state.sum);
+                    enabled = state.enabled;
+                }
+            }
+        }
+        Ok(sum)
+    }
 
-    for op in input {
+    /// Runs a single operation, failing rather than wrapping if it overflows the sum. The state
+    /// is left as it was on failure.
+    pub fn execute(&self, op: &Operation, state: &mut State) -> Result<(), OverflowError> {
         match op {
             Operation::Mul(l, r) => {
-                if enabled {
-                    sum += l * r;
+                if state.enabled {
+                    let overflow = || OverflowError {
+                        operation: op.clone(),
+                    };
+                    let product = l.checked_mul(*r).ok_or_else(overflow)?;
+                    state.sum = state.sum.checked_add(product).ok_or_else(overflow)?;
                 }
             }
             Operation::Do => {
-                enabled = true;
+                state.enabled = true;
             }
             Operation::DoNot => {
-                enabled = false;
+                state.enabled = false;
+            }
+            Operation::Custom { id, args } => {
+                let mut next = state.clone();
+                (self.instructions[*id].semantics)(args, &mut next).ok_or_else(|| {
+                    OverflowError {
+                        operation: op.clone(),
+                    }
+                })?;
+                *state = next;
+            }
+        }
+        Ok(())
+    }
+}
+
+static STANDARD: LazyLock<InstructionSet> = LazyLock::new(InstructionSet::standard);
+
+/// How `extract` finds operations in the corrupted memory, both make a single pass and yield
+/// operations in the order they appear.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ScanMode {
+    /// One regex alternating over every extractor's pattern, see `InstructionSet::extract`.
+    Regex,
+    /// Hand-rolled scanner over the input bytes.
+    Bytes,
+}
+
+fn extract(input: &str) -> Vec<Operation> {
+    extract_with(input, ScanMode::Bytes)
+}
+
+fn extract_with(input: &str, mode: ScanMode) -> Vec<Operation> {
+    match mode {
+        ScanMode::Regex => STANDARD.extract(input),
+        ScanMode::Bytes => scan(input, &ScanOptions::default()).operations,
+    }
+}
+
+/// Options for the byte scanner.
+#[derive(Debug, Clone, PartialEq)]
+struct ScanOptions {
+    /// Number of digits allowed in each `mul` operand, the puzzle allows 1 to 3.
+    operand_digits: RangeInclusive<usize>,
+    /// Whether to collect the `mul` candidates that were rejected.
+    report_rejections: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            operand_digits: 1..=3,
+            report_rejections: false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum RejectionReason {
+    /// Something other than what `mul(a,b)` needs next, `found` is `None` at the end of input.
+    Unexpected {
+        expected: &'static str,
+        found: Option<char>,
+    },
+    OperandWidth {
+        digits: usize,
+    },
+    OperandOverflow,
+}
+
+/// A `mul` that didn't make a valid instruction, `text` runs up to and including the byte that
+/// caused the rejection.
+#[derive(Debug, PartialEq)]
+struct Rejection {
+    offset: usize,
+    text: String,
+    reason: RejectionReason,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Scan {
+    operations: Vec<Operation>,
+    rejections: Vec<Rejection>,
+}
+
+enum Scanned {
+    Operation(Operation, usize),
+    /// A rejected `mul` candidate, with the length of the candidate text.
+    Rejected(RejectionReason, usize),
+    Nothing,
+}
+
+fn scan(input: &str, options: &ScanOptions) -> Scan {
+    let input = input.as_bytes();
+    let mut scan = Scan::default();
+    let mut idx = 0;
+    while idx < input.len() {
+        match scan_operation(input, idx, options) {
+            Scanned::Operation(op, len) => {
+                scan.operations.push(op);
+                idx += len;
+            }
+            Scanned::Rejected(reason, len) => {
+                if options.report_rejections {
+                    scan.rejections.push(Rejection {
+                        offset: idx,
+                        text: String::from_utf8_lossy(&input[idx..idx + len]).into_owned(),
+                        reason,
+                    });
+                }
+                // the candidate may hide a real instruction, e.g. `mul(mul(2,3)`
+                idx += 1;
+            }
+            Scanned::Nothing => idx += 1,
+        }
+    }
+    scan
+}
+
+/// Matches an operation starting at byte `start` of the input.
+fn scan_operation(input: &[u8], start: usize, options: &ScanOptions) -> Scanned {
+    let bytes = &input[start..];
+    if bytes.starts_with(b"do()") {
+        return Scanned::Operation(Operation::Do, 4);
+    }
+    if bytes.starts_with(b"don't()") {
+        return Scanned::Operation(Operation::DoNot, 7);
+    }
+    if !bytes.starts_with(b"mul") {
+        return Scanned::Nothing;
+    }
+    match scan_mul(input, start, options) {
+        Ok((op, len)) => Scanned::Operation(op, len),
+        Err((reason, len)) => Scanned::Rejected(reason, len),
+    }
+}
+
+/// Parses `mul(a,b)` at byte `start`, or explains why it isn't one.
+fn scan_mul(
+    bytes: &[u8],
+    start: usize,
+    options: &ScanOptions,
+) -> Result<(Operation, usize), (RejectionReason, usize)> {
+    let unexpected = |pos: usize, expected: &'static str| {
+        let found = char_at(bytes, pos);
+        let reason = RejectionReason::Unexpected { expected, found };
+        (reason, pos + found.map_or(0, char::len_utf8) - start)
+    };
+    let expect = |pos: usize, expected: &'static str| {
+        if bytes[pos..].starts_with(expected.as_bytes()) {
+            Ok(pos + expected.len())
+        } else {
+            Err(unexpected(pos, expected))
+        }
+    };
+    let operand = |pos: usize| {
+        let digits = bytes[pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return Err(unexpected(pos, "digit"));
+        }
+        if !options.operand_digits.contains(&digits) {
+            return Err((
+                RejectionReason::OperandWidth { digits },
+                pos + digits - start,
+            ));
+        }
+        bytes[pos..pos + digits]
+            .iter()
+            .try_fold(0usize, |acc, b| {
+                acc.checked_mul(10)?.checked_add((b - b'0') as usize)
+            })
+            .map(|value| (value, pos + digits))
+            .ok_or((RejectionReason::OperandOverflow, pos + digits - start))
+    };
+
+    let pos = expect(start + 3, "(")?;
+    let (left, pos) = operand(pos)?;
+    let pos = expect(pos, ",")?;
+    let (right, pos) = operand(pos)?;
+    let pos = expect(pos, ")")?;
+    Ok((Operation::Mul(left, right), pos - start))
+}
+
+/// The character starting at byte `pos`, `None` at the end of input or on invalid UTF-8.
+fn char_at(bytes: &[u8], pos: usize) -> Option<char> {
+    let bytes = &bytes[pos..bytes.len().min(pos + 4)];
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(valid) => valid,
+        Err(e) => std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap(),
+    };
+    valid.chars().next()
+}
+
+/// Widest operand the stream interpreter accepts, as wide as `usize::MAX`, so the bytes it holds
+/// back between chunks stay bounded.
+const MAX_STREAM_OPERAND_DIGITS: usize = 20;
+
+/// Interprets memory as it arrives in chunks, only holding on to the unscanned end of the last
+/// chunk in case an instruction is split across chunks.
+struct StreamInterpreter {
+    options: ScanOptions,
+    /// Bytes needed past the scan position before an operation there can be scanned.
+    lookahead: usize,
+    state: State,
+    pending: Vec<u8>,
+    /// Set on the first overflow, after which operations are no longer run.
+    overflow: Option<OverflowError>,
+}
+
+impl StreamInterpreter {
+    /// Fails with `InvalidInput` if operands may be wider than `MAX_STREAM_OPERAND_DIGITS`.
+    fn new(options: ScanOptions) -> io::Result<Self> {
+        let max_digits = *options.operand_digits.end();
+        if max_digits > MAX_STREAM_OPERAND_DIGITS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("can't stream operands of up to {max_digits} digits"),
+            ));
+        }
+        Ok(Self {
+            options,
+            // enough to see the widest `mul(a,b)`, plus a digit more for each operand to reject
+            // operands that are too wide
+            lookahead: (max_digits + 1) * 2 + 6,
+            state: State::default(),
+            pending: Vec::new(),
+            overflow: None,
+        })
+    }
+
+    fn feed(&mut self, chunk: &[u8]) {
+        self.pending.extend_from_slice(chunk);
+        let scanned = self.run(false);
+        self.pending.drain(..scanned);
+    }
+
+    fn finish(mut self) -> Result<usize, OverflowError> {
+        self.run(true);
+        match self.overflow {
+            Some(e) => Err(e),
+            None => Ok(self.state.sum),
+        }
+    }
+
+    /// Scans the pending bytes, stopping short of the end unless `eof` as an operation there
+    /// could continue in the next chunk. Returns the number of bytes scanned.
+    fn run(&mut self, eof: bool) -> usize {
+        let mut idx = 0;
+        while idx < self.pending.len() && (eof || self.pending.len() - idx >= self.lookahead) {
+            match scan_operation(&self.pending, idx, &self.options) {
+                Scanned::Operation(op, len) => {
+                    if self.overflow.is_none() {
+                        self.overflow = STANDARD.execute(&op, &mut self.state).err();
+                    }
+                    idx += len;
+                }
+                Scanned::Rejected(..) | Scanned::Nothing => idx += 1,
+            }
+        }
+        idx
+    }
+}
+
+/// Computes the sum for memory read in `chunk_size` pieces, using constant memory. An overflowing
+/// sum is reported as an `InvalidData` error, and a zero `chunk_size` as `InvalidInput`.
+fn compute_stream(mut reader: impl Read, chunk_size: usize) -> io::Result<usize> {
+    if chunk_size == 0 {
+        // reading into an empty buffer returns 0, which would look like the end of input
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "chunk size must be at least 1",
+        ));
+    }
+    let mut interpreter = StreamInterpreter::new(ScanOptions::default())?;
+    let mut chunk = vec![0; chunk_size];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => {
+                return interpreter
+                    .finish()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
             }
+            Ok(read) => interpreter.feed(&chunk[..read]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
         }
     }
-    sum
+}
+
+fn compute(input: &[Operation]) -> Result<usize, OverflowError> {
+    STANDARD.compute(input)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::load_input_for_day;
+    use crate::utils::{bench, load_input_for_day};
+
+    /// Matches each extractor's regex separately, then sorts the matches by position.
+    fn extract_multi_regex(input: &str) -> Vec<Operation> {
+        let mut located: Vec<(usize, Operation)> = STANDARD
+            .extractors
+            .iter()
+            .flat_map(|ext| {
+                Regex::new(ext.pattern())
+                    .unwrap()
+                    .find_iter(input)
+                    .map(|m| (m.start(), ext.extract(m.as_str())))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        located.sort_by_key(|(start, _)| *start);
+        located.into_iter().map(|(_, op)| op).collect()
+    }
 
     #[test]
     fn test_cleaning() {
         let out =
-            extract(&r"xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))");
+            extract(r"xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))");
         assert_eq!(
             out,
             vec![
@@ -121,7 +703,7 @@ mod tests {
     #[test]
     fn test_cleaning_2() {
         let out =
-            extract(&r"mul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))");
+            extract(r"mul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))");
         assert_eq!(
             out,
             vec![
@@ -135,9 +717,130 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scan_modes_match_reference() {
+        let file_contents = load_input_for_day(3);
+        let tricky = "mul(mul(1,2)do(don't()do()mul(3,4)xmul(5,6))mul(7,8";
+        let wide = "mul(1234,5)mul(123,45)mul(12,3456)mul(0,000)mul(1,0001)";
+        for input in [
+            file_contents.as_str(),
+            tricky,
+            wide,
+            "",
+            "mul(",
+            "do(don't(",
+        ] {
+            let expected = extract_multi_regex(input);
+            assert_eq!(extract_with(input, ScanMode::Regex), expected);
+            assert_eq!(extract_with(input, ScanMode::Bytes), expected);
+        }
+    }
+
+    #[test]
+    fn test_scan_operand_digits() {
+        let input = "mul(1234,5)mul(123,45)mul(99999999999999999999999,1)";
+        assert_eq!(extract(input), vec![Operation::Mul(123, 45)]);
+        let options = ScanOptions {
+            operand_digits: 1..=4,
+            ..ScanOptions::default()
+        };
+        assert_eq!(
+            scan(input, &options).operations,
+            vec![Operation::Mul(1234, 5), Operation::Mul(123, 45)]
+        );
+        let options = ScanOptions {
+            operand_digits: 1..=30,
+            report_rejections: true,
+        };
+        let scanned = scan(input, &options);
+        assert_eq!(scanned.operations.len(), 2);
+        assert_eq!(
+            scanned.rejections,
+            vec![Rejection {
+                offset: 22,
+                text: "mul(99999999999999999999999".to_string(),
+                reason: RejectionReason::OperandOverflow
+            }]
+        );
+    }
+
+    #[test]
+    fn test_scan_rejections() {
+        let options = ScanOptions {
+            report_rejections: true,
+            ..ScanOptions::default()
+        };
+        let scanned = scan(
+            "xmul(2,4)%&mul[3,7]+mul(32,64]then(mul ( 2 , 4 )mul(1234,1)mul(mul(1,2)mul(1,ä)mul(digit)mul",
+            &options,
+        );
+        assert_eq!(
+            scanned.operations,
+            vec![Operation::Mul(2, 4), Operation::Mul(1, 2)]
+        );
+        let unexpected = |expected, found| RejectionReason::Unexpected { expected, found };
+        assert_eq!(
+            scanned
+                .rejections
+                .iter()
+                .map(|r| (r.offset, r.text.as_str(), &r.reason))
+                .collect::<Vec<_>>(),
+            vec![
+                (11, "mul[", &unexpected("(", Some('['))),
+                (20, "mul(32,64]", &unexpected(")", Some(']'))),
+                (35, "mul ", &unexpected("(", Some(' '))),
+                (48, "mul(1234", &RejectionReason::OperandWidth { digits: 4 }),
+                (59, "mul(m", &unexpected("digit", Some('m'))),
+                (71, "mul(1,ä", &unexpected("digit", Some('ä'))),
+                (80, "mul(d", &unexpected("digit", Some('d'))),
+                (90, "mul", &unexpected("(", None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compute_stream() {
+        let file_contents = load_input_for_day(3);
+        let input = format!("{file_contents}mul(1,ü)mul(12,3")
+            + "don't()mul(1,2)do()mul(123,456)mul(1234,5)mul(2,3)ümul(4,5)";
+        let expected = compute(&extract(&input)).unwrap();
+        for chunk_size in [1, 2, 3, 7, 13, 64, 4096, input.len() + 1] {
+            let streamed = compute_stream(input.as_bytes(), chunk_size).unwrap();
+            assert_eq!(streamed, expected, "chunk_size: {chunk_size}");
+        }
+        assert_eq!(compute_stream(&b""[..], 16).unwrap(), 0);
+        assert_eq!(
+            compute_stream(input.as_bytes(), 0).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn test_stream_interpreter_splits() {
+        let input = b"xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        for split in 0..input.len() {
+            let mut interpreter = StreamInterpreter::new(ScanOptions::default()).unwrap();
+            interpreter.feed(&input[..split]);
+            interpreter.feed(&input[split..]);
+            assert_eq!(interpreter.finish(), Ok(48), "split: {split}");
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_extract() {
+        let file_contents = load_input_for_day(3);
+        let input = file_contents.as_str();
+        bench("multi regex", 100, || extract_multi_regex(input));
+        bench("combined regex", 100, || {
+            extract_with(input, ScanMode::Regex)
+        });
+        bench("bytes", 100, || extract_with(input, ScanMode::Bytes));
+    }
+
     #[test]
     fn test_compute() {
-        let out = compute(&vec![
+        let out = compute(&[
             Operation::Mul(2, 4),
             Operation::DoNot,
             Operation::Mul(5, 5),
@@ -145,21 +848,263 @@ mod tests {
             Operation::Do,
             Operation::Mul(8, 5),
         ]);
-        assert_eq!(out, 48);
+        assert_eq!(out, Ok(48));
+    }
+
+    #[test]
+    fn test_compute_overflow() {
+        let big = usize::MAX / 2 + 1;
+        let ops = [
+            Operation::Mul(big, 1),
+            Operation::DoNot,
+            Operation::Mul(big, 1),
+            Operation::Do,
+            Operation::Mul(big - 1, 1),
+            Operation::Mul(1, 1),
+        ];
+        assert_eq!(
+            compute(&ops),
+            Err(OverflowError {
+                operation: Operation::Mul(1, 1)
+            })
+        );
+        assert_eq!(
+            compute(&[Operation::Mul(big, 2)]).unwrap_err().to_string(),
+            format!("sum overflowed running mul({big},2)")
+        );
+        assert_eq!(
+            STANDARD.compute_exact(&ops),
+            Ok(BigUint::from(usize::MAX) + 1u32)
+        );
+        assert_eq!(
+            STANDARD.compute_exact(&[Operation::Mul(big, 2), Operation::Mul(big, 4)]),
+            Ok(BigUint::from(big) * 6u32)
+        );
+    }
+
+    #[test]
+    fn test_compute_stream_overflow() {
+        let big = usize::MAX / 2 + 1;
+        let input = format!("mul({big},1)mul({big},1)");
+        let options = ScanOptions {
+            operand_digits: 1..=20,
+            ..ScanOptions::default()
+        };
+        let mut interpreter = StreamInterpreter::new(options).unwrap();
+        interpreter.feed(input.as_bytes());
+        assert_eq!(
+            interpreter.finish(),
+            Err(OverflowError {
+                operation: Operation::Mul(big, 1)
+            })
+        );
+    }
+
+    #[test]
+    fn test_stream_interpreter_operand_width() {
+        let options = ScanOptions {
+            operand_digits: 1..=usize::MAX,
+            ..ScanOptions::default()
+        };
+        let e = StreamInterpreter::new(options).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_instruction_set() {
+        let mut set = InstructionSet::standard();
+        set.register(add_instruction())
+            .and_then(|set| {
+                set.register(Instruction::new("toggle", 0, |_, state| {
+                    state.enabled = !state.enabled;
+                    Some(())
+                }))
+            })
+            .and_then(|set| set.register(reset_instruction()))
+            .unwrap();
+        let ops = set.extract("mul(2,3)add(1,4)toggle()mul(9,9)add(1,2)toggle()reset(5)add(2,2)");
+        assert_eq!(
+            ops,
+            vec![
+                Operation::Mul(2, 3),
+                Operation::Custom {
+                    id: 0,
+                    args: vec![1, 4]
+                },
+                Operation::Custom {
+                    id: 1,
+                    args: vec![]
+                },
+                Operation::Mul(9, 9),
+                Operation::Custom {
+                    id: 0,
+                    args: vec![1, 2]
+                },
+                Operation::Custom {
+                    id: 1,
+                    args: vec![]
+                },
+                Operation::Custom {
+                    id: 2,
+                    args: vec![5]
+                },
+                Operation::Custom {
+                    id: 0,
+                    args: vec![2, 2]
+                },
+            ]
+        );
+        assert_eq!(set.compute(&ops), Ok(4));
+        assert_eq!(set.compute_exact(&ops), Ok(BigUint::from(4u32)));
+    }
+
+    fn add_instruction() -> Instruction {
+        Instruction::new("add", 2, |args, state| {
+            if state.enabled {
+                state.sum = state.sum.checked_add(args[0].checked_add(args[1])?)?;
+            }
+            Some(())
+        })
+    }
+
+    fn reset_instruction() -> Instruction {
+        Instruction::new("reset", 1, |args, state| {
+            if state.sum > args[0] {
+                state.sum = 0;
+            }
+            Some(())
+        })
+    }
+
+    #[test]
+    fn test_custom_overflow() {
+        let mut set = InstructionSet::standard();
+        set.register(add_instruction())
+            .and_then(|set| set.register(reset_instruction()))
+            .unwrap();
+        let big = usize::MAX / 2 + 1;
+        let add = Operation::Custom {
+            id: 0,
+            args: vec![big, 0],
+        };
+        let reset = Operation::Custom {
+            id: 1,
+            args: vec![0],
+        };
+
+        let mut state = State {
+            sum: big,
+            enabled: true,
+        };
+        assert_eq!(
+            set.execute(&add, &mut state),
+            Err(OverflowError {
+                operation: add.clone()
+            })
+        );
+        assert_eq!(state.sum, big);
+
+        assert_eq!(
+            set.compute_exact(&[Operation::Mul(big, 1), add.clone()]),
+            Err(OverflowError {
+                operation: add.clone()
+            })
+        );
+        assert_eq!(
+            set.compute_exact(&[Operation::Mul(big, 4), reset.clone()]),
+            Err(OverflowError { operation: reset })
+        );
+        assert_eq!(
+            set.compute_exact(&[Operation::Mul(big - 1, 1), add, Operation::Mul(big, 2)]),
+            Ok(BigUint::from(usize::MAX) + BigUint::from(big) * 2u32)
+        );
+    }
+
+    #[test]
+    fn test_register_extractor() {
+        /// Accepts `mul[a,b]` as well as `mul(a,b)`.
+        struct SquareMultiplyExtractor;
+
+        impl OperationExtractor for SquareMultiplyExtractor {
+            fn pattern(&self) -> &str {
+                r"mul\[\d+,\d+\]"
+            }
+
+            fn extract(&self, s: &str) -> Operation {
+                let (left, right) = s[4..s.len() - 1].split_once(',').unwrap();
+                Operation::Mul(left.parse().unwrap(), right.parse().unwrap())
+            }
+        }
+
+        let mut set = InstructionSet::standard();
+        set.register_extractor(SquareMultiplyExtractor).unwrap();
+        let ops = set.extract("xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)");
+        assert_eq!(
+            ops,
+            vec![
+                Operation::Mul(2, 4),
+                Operation::Mul(3, 7),
+                Operation::Mul(5, 5)
+            ]
+        );
+        assert_eq!(InstructionSet::empty().extract("mul(2,4)do()"), vec![]);
+    }
+
+    #[test]
+    fn test_register_invalid_pattern() {
+        struct BrokenExtractor;
+
+        impl OperationExtractor for BrokenExtractor {
+            fn pattern(&self) -> &str {
+                r"mul(\d+"
+            }
+
+            fn extract(&self, _s: &str) -> Operation {
+                unreachable!()
+            }
+        }
+
+        let mut set = InstructionSet::standard();
+        let e = set.register_extractor(BrokenExtractor).err().unwrap();
+        assert_eq!(e.pattern, r"mul(\d+");
+        assert!(e.to_string().starts_with(r#"invalid pattern "mul(\\d+": "#));
+        assert_eq!(
+            set.extract("mul(2,4)do()"),
+            vec![Operation::Mul(2, 4), Operation::Do]
+        );
+    }
+
+    #[test]
+    fn test_custom_argument_width() {
+        let mut set = InstructionSet::empty();
+        set.register(Instruction::new("add", 1, |_, _| Some(())))
+            .unwrap();
+        let widest = "9".repeat(usize::MAX.ilog10() as usize);
+        let input = format!("add({widest})add({})add(٣)add(7)", usize::MAX);
+        assert_eq!(
+            set.extract(&input),
+            vec![
+                Operation::Custom {
+                    id: 0,
+                    args: vec![widest.parse().unwrap()]
+                },
+                Operation::Custom {
+                    id: 0,
+                    args: vec![7]
+                },
+            ]
+        );
     }
 
     #[test]
     fn calculate_pt_1() {
         let file_contents = load_input_for_day(3);
-        let extracted = extract(file_contents.as_str())
+        let extracted: Vec<Operation> = extract(file_contents.as_str())
             .into_iter()
             // part 1 just cares about Mul operator
-            .filter(|op| match op {
-                Operation::Mul(_, _) => true,
-                _ => false,
-            })
+            .filter(|op| matches!(op, Operation::Mul(_, _)))
             .collect();
-        let total = compute(&extracted);
+        let total = compute(&extracted).unwrap();
         println!("extracted: {extracted:?}");
         println!("total: {total:?}");
         assert_eq!(total, 153469856)
@@ -169,9 +1114,10 @@ mod tests {
     fn calculate_pt_2() {
         let file_contents = load_input_for_day(3);
         let extracted = extract(file_contents.as_str());
-        let total = compute(&extracted);
+        let total = compute(&extracted).unwrap();
         println!("extracted: {extracted:?}");
         println!("total: {total:?}");
-        assert_eq!(total, 77055967)
+        assert_eq!(total, 77055967);
+        assert_eq!(STANDARD.compute_exact(&extracted), Ok(BigUint::from(total)));
     }
 }
@@ -1,6 +1,8 @@
 // Note grid here includes the \n character at the end of row
 
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 
 /// Special value to denote a break in the grid, e.g. a line break when parsing horizontally
 const BREAK: usize = usize::MAX;
@@ -45,15 +47,64 @@ impl Grid {
     }
 }
 
-impl From<&str> for Grid {
-    fn from(value: &str) -> Self {
-        let row_length = value.find('\n').unwrap_or_default();
-        let row_count = (value.len() + 1) / (row_length + 1);
-        Self {
-            values: value.replace('\n', ""),
-            width: row_length,
-            height: row_count,
+#[derive(Debug, PartialEq)]
+enum GridError {
+    Empty,
+    /// A row narrower or wider than the first, rows are numbered from 1.
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GridError::Empty => write!(f, "grid is empty"),
+            GridError::RaggedRow {
+                row,
+                expected,
+                actual,
+            } => write!(f, "row {row} has width {actual}, expected {expected}"),
+        }
+    }
+}
+
+impl Error for GridError {}
+
+impl TryFrom<&str> for Grid {
+    type Error = GridError;
+
+    /// Parses a rectangular grid, accepting `\n` or `\r\n` line endings and a single trailing
+    /// newline. Every row must have the same width as the first.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let normalised = value.replace("\r\n", "\n");
+        let trimmed = normalised.strip_suffix('\n').unwrap_or(&normalised);
+        if trimmed.is_empty() {
+            return Err(GridError::Empty);
+        }
+        let mut values = String::with_capacity(trimmed.len());
+        let mut width = 0;
+        let mut height = 0;
+        for (row, line) in trimmed.split('\n').enumerate() {
+            if row == 0 {
+                width = line.len();
+            } else if line.len() != width {
+                return Err(GridError::RaggedRow {
+                    row: row + 1,
+                    expected: width,
+                    actual: line.len(),
+                });
+            }
+            values.push_str(line);
+            height += 1;
         }
+        Ok(Self {
+            values,
+            width,
+            height,
+        })
     }
 }
 
@@ -281,12 +332,48 @@ mod tests {
 
     #[test]
     fn test_grid() {
-        let grid = Grid::from("xmas\nsamx");
+        let grid = Grid::try_from("xmas\nsamx").unwrap();
         assert_eq!(grid.width, 4);
         assert_eq!(grid.height, 2);
         assert_eq!(grid.values, "xmassamx".to_string());
     }
 
+    #[test]
+    fn test_grid_line_endings() {
+        for input in ["xmas\nsamx\n", "xmas\r\nsamx", "xmas\r\nsamx\r\n"] {
+            let grid = Grid::try_from(input).unwrap();
+            assert_eq!(grid.width, 4, "input: {input:?}");
+            assert_eq!(grid.height, 2, "input: {input:?}");
+            assert_eq!(grid.values, "xmassamx".to_string(), "input: {input:?}");
+        }
+    }
+
+    #[test]
+    fn test_grid_invalid() {
+        assert_eq!(Grid::try_from("").err(), Some(GridError::Empty));
+        assert_eq!(Grid::try_from("\r\n").err(), Some(GridError::Empty));
+        assert_eq!(
+            Grid::try_from("xmas\nsamx\nxma\nsamx").err(),
+            Some(GridError::RaggedRow {
+                row: 3,
+                expected: 4,
+                actual: 3
+            })
+        );
+        assert_eq!(
+            Grid::try_from("xmas\nsamx\n\n").err(),
+            Some(GridError::RaggedRow {
+                row: 3,
+                expected: 4,
+                actual: 0
+            })
+        );
+        assert_eq!(
+            Grid::try_from("xmas\nsamx\nxma").err().unwrap().to_string(),
+            "row 3 has width 3, expected 4"
+        );
+    }
+
     #[test]
     fn test_grid_direction_iterator_horizontal() {
         let grid_indexes = GridDirection::Horizontal
@@ -361,7 +448,7 @@ mod tests {
     #[test]
     fn test_grid_small() {
         let raw_grid = load_file(4, "input_test_4x4.txt");
-        let grid = Grid::from(raw_grid.as_str());
+        let grid = Grid::try_from(raw_grid.as_str()).unwrap();
         let count = grid.count_xmas_words();
         assert_eq!(count, 6);
     }
@@ -369,7 +456,7 @@ mod tests {
     #[test]
     fn test_grid_small_mas() {
         let raw_grid = load_file(4, "input_test_4x4.txt");
-        let grid = Grid::from(raw_grid.as_str());
+        let grid = Grid::try_from(raw_grid.as_str()).unwrap();
         let count = grid.count_mas_crosses();
         assert_eq!(count, 2);
     }
@@ -377,7 +464,7 @@ mod tests {
     #[test]
     fn test_example_grid() {
         let raw_grid = load_file(4, "input_test.txt");
-        let grid = Grid::from(raw_grid.as_str());
+        let grid = Grid::try_from(raw_grid.as_str()).unwrap();
         let count = grid.count_xmas_words();
         assert_eq!(count, 18);
     }
@@ -385,7 +472,7 @@ mod tests {
     #[test]
     fn test_example_grid_mas_cross() {
         let raw_grid = load_file(4, "input_test.txt");
-        let grid = Grid::from(raw_grid.as_str());
+        let grid = Grid::try_from(raw_grid.as_str()).unwrap();
         let count = grid.count_mas_crosses();
         assert_eq!(count, 9);
     }
@@ -393,7 +480,7 @@ mod tests {
     #[test]
     fn calculate_pt_1() {
         let raw_grid = load_input_for_day(4);
-        let grid = Grid::from(raw_grid.as_str());
+        let grid = Grid::try_from(raw_grid.as_str()).unwrap();
         let count = grid.count_xmas_words();
         assert_eq!(count, 2468);
     }
@@ -401,7 +488,7 @@ mod tests {
     #[test]
     fn calculate_pt_2() {
         let raw_grid = load_input_for_day(4);
-        let grid = Grid::from(raw_grid.as_str());
+        let grid = Grid::try_from(raw_grid.as_str()).unwrap();
         let count = grid.count_mas_crosses();
         assert_eq!(count, 1864);
     }
@@ -0,0 +1,92 @@
+use super::graph::{CycleError, RuleGraph};
+use super::matrix::RuleMatrix;
+
+/// Transitive closure of the ordering rules, `(a, b)` is set when page `a` must come before page
+/// `b`, directly or through a chain of rules. Only defined for acyclic rules, as a cycle would
+/// put every page it reaches before itself.
+#[derive(Debug)]
+pub(super) struct ClosureIndex {
+    reachable: RuleMatrix,
+}
+
+impl ClosureIndex {
+    pub(super) fn new(graph: &RuleGraph) -> Result<Self, CycleError> {
+        if let Some(cycle) = graph.find_cycle() {
+            return Err(CycleError { cycle });
+        }
+        let rules = graph.rules();
+        let pages = rules.pages();
+        let mut reachable = RuleMatrix::with_pages(&pages);
+        for &start in &pages {
+            let mut stack: Vec<usize> = rules.successors(start).collect();
+            while let Some(page) = stack.pop() {
+                if !reachable.contains(start, page) {
+                    reachable.insert(start, page);
+                    stack.extend(rules.successors(page));
+                }
+            }
+        }
+        Ok(Self { reachable })
+    }
+
+    /// Whether the rules imply `before` must come before `after`, O(1).
+    pub(super) fn must_precede(&self, before: usize, after: usize) -> bool {
+        self.reachable.contains(before, after)
+    }
+
+    /// Checks the update against every implied rule rather than only the direct ones.
+    pub(super) fn is_ordered(&self, update: &[usize]) -> bool {
+        self.reachable.is_ordered(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closure(rules: &[(usize, usize)]) -> Result<ClosureIndex, CycleError> {
+        let mut matrix = RuleMatrix::default();
+        for &(before, after) in rules {
+            matrix.insert(before, after);
+        }
+        ClosureIndex::new(&RuleGraph::new(&matrix))
+    }
+
+    #[test]
+    fn test_must_precede() {
+        let closure = closure(&[(1, 2), (2, 3), (3, 70), (5, 1)]).unwrap();
+        assert!(closure.must_precede(1, 2));
+        assert!(closure.must_precede(1, 70));
+        assert!(closure.must_precede(5, 70));
+        assert!(!closure.must_precede(70, 1));
+        assert!(!closure.must_precede(2, 1));
+        assert!(!closure.must_precede(1, 1));
+        assert!(!closure.must_precede(100, 1));
+        assert!(!closure.must_precede(1, 100));
+    }
+
+    #[test]
+    fn test_must_precede_cycle() {
+        assert_eq!(
+            closure(&[(1, 2), (2, 3), (3, 1), (5, 1)]).err(),
+            Some(CycleError {
+                cycle: vec![1, 2, 3]
+            })
+        );
+    }
+
+    #[test]
+    fn test_large_page_ids() {
+        let closure = closure(&[(1, 1_000_000), (1_000_000, usize::MAX)]).unwrap();
+        assert!(closure.must_precede(1, usize::MAX));
+        assert!(!closure.must_precede(usize::MAX, 1));
+    }
+
+    #[test]
+    fn test_is_ordered() {
+        let closure = closure(&[(1, 2), (2, 3)]).unwrap();
+        assert!(closure.is_ordered(&[1, 3]));
+        assert!(closure.is_ordered(&[1, 4, 3]));
+        assert!(!closure.is_ordered(&[3, 1]));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::utils::SplitMix64;
+use itertools::Itertools;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone)]
+pub(super) struct GeneratorConfig {
+    pub(super) seed: u64,
+    /// Number of distinct pages, ids start at 10 so the puzzle's two digit pages come first.
+    pub(super) pages: usize,
+    /// Fraction of the rules implied by the hidden order that are written out, on top of the
+    /// rules each update needs for its corrected order to be unique.
+    pub(super) rule_fraction: f64,
+    pub(super) updates: usize,
+    pub(super) update_length: RangeInclusive<usize>,
+    /// Fraction of the updates that are out of order, rounded to the nearest update.
+    pub(super) invalid_fraction: f64,
+}
+
+impl Default for GeneratorConfig {
+    /// Roughly the shape of the real puzzle input.
+    fn default() -> Self {
+        Self {
+            seed: 2024,
+            pages: 49,
+            rule_fraction: 1.0,
+            updates: 200,
+            update_length: 5..=23,
+            invalid_fraction: 0.5,
+        }
+    }
+}
+
+/// A generated puzzle input along with the answers it should give.
+#[derive(Debug)]
+pub(super) struct GeneratedInput {
+    pub(super) input: String,
+    pub(super) validate: usize,
+    pub(super) fix: usize,
+    pub(super) invalid_updates: usize,
+}
+
+/// Generates a puzzle input from a hidden total order over the pages, so the rules can never
+/// contain a cycle.
+pub(super) fn generate(config: &GeneratorConfig) -> GeneratedInput {
+    assert!(*config.update_length.start() >= 1 && *config.update_length.end() <= config.pages);
+    let mut rng = SplitMix64::new(config.seed);
+    let mut order: Vec<usize> = (10..10 + config.pages).collect();
+    rng.shuffle(&mut order);
+    let mut rank = vec![0; 10 + config.pages];
+    for (idx, &page) in order.iter().enumerate() {
+        rank[page] = idx;
+    }
+
+    let invalid_updates = (config.updates as f64 * config.invalid_fraction).round() as usize;
+    let mut is_invalid: Vec<bool> = (0..config.updates)
+        .map(|idx| idx < invalid_updates)
+        .collect();
+    rng.shuffle(&mut is_invalid);
+
+    let mut rules = HashSet::new();
+    let mut updates = Vec::with_capacity(config.updates);
+    let (mut validate, mut fix) = (0, 0);
+    for invalid in is_invalid {
+        // an update needs two pages to be out of order
+        let start = (*config.update_length.start()).max(if invalid { 2 } else { 1 });
+        let end = (*config.update_length.end()).max(start);
+        let length = start + rng.below(end - start + 1);
+        let mut pages = order.clone();
+        rng.shuffle(&mut pages);
+        pages.truncate(length);
+        pages.sort_unstable_by_key(|&page| rank[page]);
+
+        // chaining each page to the next pins down a unique corrected order
+        rules.extend(pages.iter().copied().tuple_windows::<(usize, usize)>());
+        let middle = pages[pages.len() / 2];
+        if invalid {
+            fix += middle;
+            let sorted = pages.clone();
+            while pages == sorted {
+                rng.shuffle(&mut pages);
+            }
+        } else {
+            validate += middle;
+        }
+        updates.push(pages);
+    }
+
+    for (idx, &before) in order.iter().enumerate() {
+        for &after in &order[idx + 1..] {
+            if rng.chance(config.rule_fraction) {
+                rules.insert((before, after));
+            }
+        }
+    }
+    let mut rules: Vec<(usize, usize)> = rules.into_iter().collect();
+    rules.sort_unstable();
+    rng.shuffle(&mut rules);
+
+    let input = format!(
+        "{}\n\n{}",
+        rules
+            .iter()
+            .map(|(before, after)| format!("{before}|{after}"))
+            .join("\n"),
+        updates
+            .iter()
+            .map(|update| update.iter().join(","))
+            .join("\n")
+    );
+    GeneratedInput {
+        input,
+        validate,
+        fix,
+        invalid_updates,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day5::parse;
+
+    #[test]
+    fn test_generate() {
+        for (rule_fraction, invalid_fraction) in [(1.0, 0.5), (0.0, 0.3), (0.2, 1.0), (0.5, 0.0)] {
+            let config = GeneratorConfig {
+                rule_fraction,
+                invalid_fraction,
+                ..GeneratorConfig::default()
+            };
+            let generated = generate(&config);
+            let page_updates = parse(&generated.input);
+            assert_eq!(page_updates.validate(), generated.validate);
+            assert_eq!(page_updates.fix(), Ok(generated.fix));
+            assert_eq!(
+                page_updates
+                    .updates
+                    .iter()
+                    .filter(|update| !page_updates.is_update_valid(update))
+                    .count(),
+                generated.invalid_updates
+            );
+            assert_eq!(
+                generated.invalid_updates,
+                (config.updates as f64 * invalid_fraction).round() as usize
+            );
+            assert_eq!(page_updates.graph().find_cycle(), None);
+        }
+    }
+
+    #[test]
+    fn test_generate_is_seeded() {
+        let config = GeneratorConfig::default();
+        assert_eq!(generate(&config).input, generate(&config).input);
+        let reseeded = GeneratorConfig {
+            seed: 7,
+            ..config.clone()
+        };
+        assert_ne!(generate(&config).input, generate(&reseeded).input);
+    }
+}
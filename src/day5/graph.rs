@@ -0,0 +1,502 @@
+use super::matrix::RuleMatrix;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+use std::error::Error;
+use std::fmt;
+
+/// The rules between a set of pages contain a cycle, so the pages have no valid order.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct CycleError {
+    /// Pages in the cycle, each page must come before the next and the last before the first.
+    pub(super) cycle: Vec<usize>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ordering rules contain a cycle: ")?;
+        for page in &self.cycle {
+            write!(f, "{page} -> ")?;
+        }
+        match self.cycle.first() {
+            Some(first) => write!(f, "{first}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Error for CycleError {}
+
+/// Most subsets of an update's pages visited while counting its orders, past this only a lower
+/// bound on the count is given.
+const MAX_COUNT_STATES: usize = 1 << 16;
+
+/// Number of orders of an update's pages that satisfy every rule between them.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) enum OrderingCount {
+    Exact(u128),
+    /// Too many orders to count, or more than fit in a `u128`.
+    AtLeast(u128),
+}
+
+/// How tightly the rules pin down the order of an update's pages.
+#[derive(Debug, PartialEq)]
+pub(super) struct OrderingAnalysis {
+    pub(super) count: OrderingCount,
+    /// Pages that are in the middle of at least one valid order.
+    pub(super) middle_pages: BTreeSet<usize>,
+}
+
+impl OrderingAnalysis {
+    pub(super) fn is_unique(&self) -> bool {
+        self.count == OrderingCount::Exact(1)
+    }
+}
+
+/// The rules between an update's pages, by index in the update.
+struct UpdateRules {
+    predecessors: Vec<Vec<usize>>,
+    successors: Vec<Vec<usize>>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Visit {
+    InProgress,
+    Done,
+}
+
+/// Graph algorithms over the page ordering rules, following the rows of a `RuleMatrix`. An edge
+/// `a -> b` means page `a` must be printed before page `b`.
+#[derive(Debug, Copy, Clone)]
+pub(super) struct RuleGraph<'a> {
+    rules: &'a RuleMatrix,
+}
+
+impl<'a> RuleGraph<'a> {
+    pub(super) fn new(rules: &'a RuleMatrix) -> Self {
+        Self { rules }
+    }
+
+    pub(super) fn rules(&self) -> &'a RuleMatrix {
+        self.rules
+    }
+
+    pub(super) fn successors(&self, page: usize) -> impl Iterator<Item = usize> + 'a {
+        self.rules.successors(page)
+    }
+
+    /// Orders the update's pages using Kahn's algorithm on the subgraph induced by those pages.
+    /// Ties are broken by the original position in the update, so an update that is already
+    /// ordered is returned unchanged.
+    pub(super) fn topological_sort(&self, update: &[usize]) -> Result<Vec<usize>, CycleError> {
+        let rules = self.update_rules(update);
+        let mut in_degree: Vec<usize> = rules.predecessors.iter().map(Vec::len).collect();
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..update.len())
+            .filter(|&idx| in_degree[idx] == 0)
+            .map(Reverse)
+            .collect();
+        let mut sorted = Vec::with_capacity(update.len());
+        while let Some(Reverse(idx)) = ready.pop() {
+            sorted.push(update[idx]);
+            for &after_idx in &rules.successors[idx] {
+                in_degree[after_idx] -= 1;
+                if in_degree[after_idx] == 0 {
+                    ready.push(Reverse(after_idx));
+                }
+            }
+        }
+
+        if sorted.len() < update.len() {
+            // every page left over is on, or downstream of, a cycle
+            let remaining: Vec<usize> = (0..update.len())
+                .filter(|&idx| in_degree[idx] > 0)
+                .map(|idx| update[idx])
+                .collect();
+            let cycle = self
+                .find_cycle_in(&remaining)
+                .expect("pages left over by Kahn's algorithm must contain a cycle");
+            return Err(CycleError { cycle });
+        }
+        Ok(sorted)
+    }
+
+    /// Counts the valid orders of the update's pages and which pages can end up in the middle.
+    /// Counting is exponential in the number of unconstrained pages, so it gives up after
+    /// `MAX_COUNT_STATES` and reports a lower bound instead. Uniqueness and the middle pages are
+    /// always exact.
+    pub(super) fn analyse_ordering(
+        &self,
+        update: &[usize],
+    ) -> Result<OrderingAnalysis, CycleError> {
+        let sorted = self.topological_sort(update)?;
+        let rules = self.update_rules(update);
+
+        // the order is unique exactly when each page of the sorted order must come before the
+        // next, otherwise the two could be swapped
+        let unique = sorted
+            .windows(2)
+            .all(|pair| self.rules.contains(pair[0], pair[1]));
+        let count = if unique {
+            OrderingCount::Exact(1)
+        } else {
+            let mut memo = HashMap::new();
+            let placed = vec![0; update.len().div_ceil(64)];
+            match count_orderings(&rules.predecessors, placed, 0, &mut memo) {
+                Some(count) if count < u128::MAX => OrderingCount::Exact(count),
+                _ => OrderingCount::AtLeast(orderings_lower_bound(&rules).max(2)),
+            }
+        };
+
+        // a page fits at a position of some valid order exactly when every page it must come
+        // after fits in front of it and every page it must come before fits behind it
+        let middle = update.len().div_euclid(2);
+        let middle_pages = (0..update.len())
+            .filter(|&idx| {
+                let ancestors = reachable(&rules.predecessors, idx);
+                let descendants = reachable(&rules.successors, idx);
+                ancestors <= middle && middle < update.len() - descendants
+            })
+            .map(|idx| update[idx])
+            .collect();
+
+        Ok(OrderingAnalysis {
+            count,
+            middle_pages,
+        })
+    }
+
+    /// Lists up to `limit` valid orders of the update's pages.
+    pub(super) fn orderings(
+        &self,
+        update: &[usize],
+        limit: usize,
+    ) -> Result<Vec<Vec<usize>>, CycleError> {
+        self.topological_sort(update)?;
+        let rules = self.update_rules(update);
+        let mut orderings = Vec::new();
+        let mut placed = vec![false; update.len()];
+        let mut current = Vec::with_capacity(update.len());
+        enumerate_orderings(
+            &rules.predecessors,
+            &mut placed,
+            &mut current,
+            &mut orderings,
+            limit,
+        );
+        Ok(orderings
+            .into_iter()
+            .map(|order| order.into_iter().map(|idx| update[idx]).collect())
+            .collect())
+    }
+
+    /// Looks up the rule between every pair of the update's pages, O(n²) matrix lookups.
+    fn update_rules(&self, update: &[usize]) -> UpdateRules {
+        let mut rules = UpdateRules {
+            predecessors: vec![Vec::new(); update.len()],
+            successors: vec![Vec::new(); update.len()],
+        };
+        for (idx, &page) in update.iter().enumerate() {
+            for (after_idx, &after) in update.iter().enumerate() {
+                if self.rules.contains(page, after) {
+                    rules.predecessors[after_idx].push(idx);
+                    rules.successors[idx].push(after_idx);
+                }
+            }
+        }
+        rules
+    }
+
+    /// Finds a cycle anywhere in the rules.
+    pub(super) fn find_cycle(&self) -> Option<Vec<usize>> {
+        self.find_cycle_from(&self.rules.pages(), |_| true)
+    }
+
+    /// Finds a cycle in the subgraph induced by the given pages, i.e. only rules where both
+    /// pages are in `pages` are followed.
+    pub(super) fn find_cycle_in(&self, pages: &[usize]) -> Option<Vec<usize>> {
+        let positions = positions(pages);
+        self.find_cycle_from(pages, |page| positions.contains_key(&page))
+    }
+
+    /// Iterative depth first search, a rule leading back to a page still on the stack closes a
+    /// cycle.
+    fn find_cycle_from(
+        &self,
+        pages: &[usize],
+        include: impl Fn(usize) -> bool,
+    ) -> Option<Vec<usize>> {
+        let mut visits: HashMap<usize, Visit> = HashMap::new();
+        for &start in pages {
+            if visits.contains_key(&start) {
+                continue;
+            }
+            visits.insert(start, Visit::InProgress);
+            // (page, its successors, index of the next successor to follow)
+            let mut stack = vec![(start, self.successors(start).collect::<Vec<_>>(), 0)];
+            while let Some((page, successors, next)) = stack.last_mut() {
+                let Some(&after) = successors.get(*next) else {
+                    visits.insert(*page, Visit::Done);
+                    stack.pop();
+                    continue;
+                };
+                *next += 1;
+                if !include(after) {
+                    continue;
+                }
+                match visits.get(&after) {
+                    Some(Visit::InProgress) => {
+                        let cycle_start = stack.iter().position(|(p, ..)| *p == after)?;
+                        return Some(stack[cycle_start..].iter().map(|(p, ..)| *p).collect());
+                    }
+                    Some(Visit::Done) => {}
+                    None => {
+                        visits.insert(after, Visit::InProgress);
+                        stack.push((after, self.successors(after).collect(), 0));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Number of pages reachable from `idx` by following `edges`.
+fn reachable(edges: &[Vec<usize>], idx: usize) -> usize {
+    let mut seen = vec![false; edges.len()];
+    let mut stack = edges[idx].clone();
+    let mut count = 0;
+    while let Some(next) = stack.pop() {
+        if !seen[next] {
+            seen[next] = true;
+            count += 1;
+            stack.extend(&edges[next]);
+        }
+    }
+    count
+}
+
+/// Number of ways to place the pages not yet in `placed`, a bitset of update indexes, memoised
+/// on the placed set. Counts saturate at `u128::MAX`, `None` once `memo` holds
+/// `MAX_COUNT_STATES` sets.
+fn count_orderings(
+    predecessors: &[Vec<usize>],
+    placed: Vec<u64>,
+    placed_count: usize,
+    memo: &mut HashMap<Vec<u64>, u128>,
+) -> Option<u128> {
+    if placed_count == predecessors.len() {
+        return Some(1);
+    }
+    if let Some(&count) = memo.get(&placed) {
+        return Some(count);
+    }
+    if memo.len() >= MAX_COUNT_STATES {
+        return None;
+    }
+    let is_placed = |idx: usize| placed[idx / 64] & (1 << (idx % 64)) != 0;
+    let mut count = 0u128;
+    for idx in 0..predecessors.len() {
+        if is_placed(idx) || !predecessors[idx].iter().all(|&before| is_placed(before)) {
+            continue;
+        }
+        let mut next = placed.clone();
+        next[idx / 64] |= 1 << (idx % 64);
+        let ways = count_orderings(predecessors, next, placed_count + 1, memo)?;
+        count = count.saturating_add(ways);
+    }
+    memo.insert(placed, count);
+    Some(count)
+}
+
+/// A lower bound on the number of valid orders: pages with no rules to the others can go
+/// anywhere in a fixed order of the rest, giving n! / (n - free)! orders.
+fn orderings_lower_bound(rules: &UpdateRules) -> u128 {
+    let pages = rules.predecessors.len();
+    let free = (0..pages)
+        .filter(|&idx| rules.predecessors[idx].is_empty() && rules.successors[idx].is_empty())
+        .count();
+    (pages - free + 1..=pages).fold(1u128, |bound, factor| bound.saturating_mul(factor as u128))
+}
+
+fn enumerate_orderings(
+    predecessors: &[Vec<usize>],
+    placed: &mut [bool],
+    current: &mut Vec<usize>,
+    orderings: &mut Vec<Vec<usize>>,
+    limit: usize,
+) {
+    if orderings.len() >= limit {
+        return;
+    }
+    if current.len() == predecessors.len() {
+        orderings.push(current.clone());
+        return;
+    }
+    for idx in 0..predecessors.len() {
+        if !placed[idx] && predecessors[idx].iter().all(|&before| placed[before]) {
+            placed[idx] = true;
+            current.push(idx);
+            enumerate_orderings(predecessors, placed, current, orderings, limit);
+            current.pop();
+            placed[idx] = false;
+        }
+    }
+}
+
+fn positions(update: &[usize]) -> HashMap<usize, usize> {
+    update
+        .iter()
+        .enumerate()
+        .map(|(idx, &page)| (page, idx))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(rules: &[(usize, usize)]) -> RuleMatrix {
+        let mut matrix = RuleMatrix::default();
+        for &(before, after) in rules {
+            matrix.insert(before, after);
+        }
+        matrix
+    }
+
+    #[test]
+    fn test_removed_rules_are_not_followed() {
+        let mut rules = matrix(&[(1, 2), (2, 3), (3, 1)]);
+        rules.remove(3, 1);
+        let graph = RuleGraph::new(&rules);
+        assert_eq!(graph.find_cycle(), None);
+        assert_eq!(graph.topological_sort(&[3, 2, 1]), Ok(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_topological_sort() {
+        let rules = matrix(&[(1, 2), (2, 3), (4, 1)]);
+        let graph = RuleGraph::new(&rules);
+        assert_eq!(graph.topological_sort(&[3, 2, 1, 4]), Ok(vec![4, 1, 2, 3]));
+        // only rules between the update's pages are considered
+        assert_eq!(graph.topological_sort(&[3, 4]), Ok(vec![3, 4]));
+        assert_eq!(graph.topological_sort(&[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_topological_sort_ordered_update() {
+        let rules = matrix(&[(1, 2), (3, 4)]);
+        let graph = RuleGraph::new(&rules);
+        assert_eq!(graph.topological_sort(&[1, 3, 2, 4]), Ok(vec![1, 3, 2, 4]));
+        assert_eq!(graph.topological_sort(&[3, 1, 4, 2]), Ok(vec![3, 1, 4, 2]));
+    }
+
+    #[test]
+    fn test_topological_sort_cycle() {
+        let rules = matrix(&[(1, 2), (2, 3), (3, 1), (4, 2)]);
+        let graph = RuleGraph::new(&rules);
+        let err = graph.topological_sort(&[3, 4, 2, 1]).unwrap_err();
+        assert_eq!(err.cycle, vec![3, 1, 2]);
+        assert_eq!(
+            err.to_string(),
+            "ordering rules contain a cycle: 3 -> 1 -> 2 -> 3"
+        );
+        // the cycle is broken when one of its pages is missing from the update
+        assert_eq!(graph.topological_sort(&[4, 3, 2]), Ok(vec![4, 2, 3]));
+    }
+
+    #[test]
+    fn test_analyse_ordering() {
+        let rules = matrix(&[(1, 2), (2, 3), (1, 4)]);
+        let graph = RuleGraph::new(&rules);
+        let analysis = graph.analyse_ordering(&[3, 2, 1]).unwrap();
+        assert!(analysis.is_unique());
+        assert_eq!(analysis.middle_pages, BTreeSet::from([2]));
+
+        // 1 is first, the rest can be any interleaving of 2, 3 and 4 with 2 before 3
+        let analysis = graph.analyse_ordering(&[1, 2, 3, 4]).unwrap();
+        assert_eq!(analysis.count, OrderingCount::Exact(3));
+        assert!(!analysis.is_unique());
+        assert_eq!(analysis.middle_pages, BTreeSet::from([2, 3, 4]));
+
+        let analysis = graph.analyse_ordering(&[5, 6, 7]).unwrap();
+        assert_eq!(analysis.count, OrderingCount::Exact(6));
+        assert_eq!(analysis.middle_pages, BTreeSet::from([5, 6, 7]));
+    }
+
+    #[test]
+    fn test_analyse_ordering_long_updates() {
+        // a chain longer than 64 pages has one order
+        let chain: Vec<(usize, usize)> = (0..99).map(|page| (page, page + 1)).collect();
+        let update: Vec<usize> = (0..100).rev().collect();
+        let analysis = RuleGraph::new(&matrix(&chain))
+            .analyse_ordering(&update)
+            .unwrap();
+        assert!(analysis.is_unique());
+        assert_eq!(analysis.middle_pages, BTreeSet::from([50]));
+
+        // 70 pages and a single rule is too many orders to count
+        let rules = matrix(&[(1, 2)]);
+        let graph = RuleGraph::new(&rules);
+        let update: Vec<usize> = (0..70).collect();
+        let analysis = graph.analyse_ordering(&update).unwrap();
+        assert_eq!(analysis.count, OrderingCount::AtLeast(u128::MAX));
+        assert_eq!(analysis.middle_pages.len(), 70);
+        let update: Vec<usize> = (0..20).collect();
+        let factorial = (2..=20).product::<u128>();
+        assert_eq!(
+            graph.analyse_ordering(&update).unwrap().count,
+            OrderingCount::AtLeast(factorial / 2)
+        );
+        // few enough free pages are still counted exactly
+        let update: Vec<usize> = (0..8).collect();
+        assert_eq!(
+            graph.analyse_ordering(&update).unwrap().count,
+            OrderingCount::Exact((2..=8).product::<u128>() / 2)
+        );
+        assert_eq!(graph.orderings(&update, 3).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_analyse_ordering_cycle() {
+        let rules = matrix(&[(1, 2), (2, 1)]);
+        let graph = RuleGraph::new(&rules);
+        assert!(graph.analyse_ordering(&[1, 2]).is_err());
+        assert!(graph.orderings(&[1, 2], 10).is_err());
+    }
+
+    #[test]
+    fn test_orderings() {
+        let rules = matrix(&[(1, 2), (1, 3)]);
+        let graph = RuleGraph::new(&rules);
+        assert_eq!(
+            graph.orderings(&[3, 2, 1], 10),
+            Ok(vec![vec![1, 3, 2], vec![1, 2, 3]])
+        );
+        assert_eq!(graph.orderings(&[3, 2, 1], 1), Ok(vec![vec![1, 3, 2]]));
+        assert_eq!(graph.orderings(&[], 10), Ok(vec![vec![]]));
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        assert_eq!(
+            RuleGraph::new(&matrix(&[(1, 2), (2, 3), (1, 3)])).find_cycle(),
+            None
+        );
+        assert_eq!(
+            RuleGraph::new(&matrix(&[(1, 2), (2, 3), (3, 4), (4, 2)])).find_cycle(),
+            Some(vec![2, 3, 4])
+        );
+        assert_eq!(
+            RuleGraph::new(&matrix(&[(5, 5)])).find_cycle(),
+            Some(vec![5])
+        );
+    }
+
+    #[test]
+    fn test_find_cycle_in() {
+        let rules = matrix(&[(1, 2), (2, 3), (3, 1), (3, 4), (4, 2)]);
+        let graph = RuleGraph::new(&rules);
+        assert_eq!(graph.find_cycle_in(&[1, 2, 4]), None);
+        assert_eq!(graph.find_cycle_in(&[4, 2, 3]), Some(vec![4, 2, 3]));
+        assert_eq!(graph.find_cycle_in(&[]), None);
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+/// Page ids below this find their dense index in a table, larger ones in a `HashMap`.
+const DIRECT_PAGES: usize = 1 << 16;
+const NO_INDEX: u32 = u32::MAX;
+
+/// Square bit matrix over the pages seen so far, bit `(a, b)` is set when page `a` must come
+/// before page `b`. Pages get dense indexes in the order they're first inserted, so the matrix
+/// grows with the number of pages rather than the size of their ids, and the usual small ids
+/// are far cheaper to query than hashing pairs.
+#[derive(Debug, Default, Clone)]
+pub(super) struct RuleMatrix {
+    /// Dense index of each page id below `DIRECT_PAGES`, or `NO_INDEX`.
+    direct: Vec<u32>,
+    /// Dense index of larger page ids.
+    sparse: HashMap<usize, usize>,
+    /// Page id at each dense index.
+    pages: Vec<usize>,
+    /// Rows allocated, each `words` long.
+    capacity: usize,
+    words: usize,
+    bits: Vec<u64>,
+}
+
+impl RuleMatrix {
+    /// An empty matrix with the given pages already indexed.
+    pub(super) fn with_pages(pages: &[usize]) -> Self {
+        let mut matrix = Self::default();
+        matrix.grow(pages.len());
+        for &page in pages {
+            matrix.index_or_insert(page);
+        }
+        matrix
+    }
+
+    /// Number of pages covered.
+    pub(super) fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Sets `(before, after)`, indexing either page if it's new.
+    pub(super) fn insert(&mut self, before: usize, after: usize) {
+        let before = self.index_or_insert(before);
+        let after = self.index_or_insert(after);
+        self.bits[before * self.words + after / 64] |= 1 << (after % 64);
+    }
+
+    pub(super) fn remove(&mut self, before: usize, after: usize) {
+        if let (Some(before), Some(after)) = (self.index(before), self.index(after)) {
+            self.bits[before * self.words + after / 64] &= !(1 << (after % 64));
+        }
+    }
+
+    pub(super) fn contains(&self, before: usize, after: usize) -> bool {
+        match (self.index(before), self.index(after)) {
+            (Some(before), Some(after)) => self.is_set(before, after),
+            _ => false,
+        }
+    }
+
+    /// Pages `page` must come before, in the order they were first indexed.
+    pub(super) fn successors(&self, page: usize) -> impl Iterator<Item = usize> + '_ {
+        let row = self.index(page).map_or(&[][..], |idx| self.row(idx));
+        row.iter().enumerate().flat_map(move |(word, &bits)| {
+            let mut bits = bits;
+            std::iter::from_fn(move || {
+                let bit = bits.trailing_zeros() as usize;
+                (bits != 0).then(|| {
+                    bits &= bits - 1;
+                    self.pages[word * 64 + bit]
+                })
+            })
+        })
+    }
+
+    /// Every page in at least one set pair, in ascending order.
+    pub(super) fn pages(&self) -> Vec<usize> {
+        let mut pages: Vec<usize> = self
+            .iter()
+            .flat_map(|(before, after)| [before, after])
+            .collect();
+        pages.sort_unstable();
+        pages.dedup();
+        pages
+    }
+
+    /// All set pairs in ascending order.
+    pub(super) fn iter(&self) -> impl Iterator<Item = (usize, usize)> {
+        let mut pairs: Vec<(usize, usize)> = (0..self.len())
+            .flat_map(|before| {
+                (0..self.len())
+                    .filter(move |&after| self.is_set(before, after))
+                    .map(move |after| (self.pages[before], self.pages[after]))
+            })
+            .collect();
+        pairs.sort_unstable();
+        pairs.into_iter()
+    }
+
+    /// Checks that no page is followed by a page it must come after, O(n) bitset operations.
+    pub(super) fn is_ordered(&self, update: &[usize]) -> bool {
+        let mut seen = vec![0u64; self.words];
+        for &page in update {
+            let Some(idx) = self.index(page) else {
+                continue;
+            };
+            if self
+                .row(idx)
+                .iter()
+                .zip(&seen)
+                .any(|(row, seen)| row & seen != 0)
+            {
+                return false;
+            }
+            seen[idx / 64] |= 1 << (idx % 64);
+        }
+        true
+    }
+
+    fn index(&self, page: usize) -> Option<usize> {
+        if page < DIRECT_PAGES {
+            match self.direct.get(page) {
+                Some(&idx) if idx != NO_INDEX => Some(idx as usize),
+                _ => None,
+            }
+        } else {
+            self.sparse.get(&page).copied()
+        }
+    }
+
+    fn index_or_insert(&mut self, page: usize) -> usize {
+        if let Some(idx) = self.index(page) {
+            return idx;
+        }
+        let idx = self.pages.len();
+        self.pages.push(page);
+        if page < DIRECT_PAGES {
+            if page >= self.direct.len() {
+                self.direct.resize(page + 1, NO_INDEX);
+            }
+            self.direct[page] = idx as u32;
+        } else {
+            self.sparse.insert(page, idx);
+        }
+        if idx >= self.capacity {
+            self.grow((self.capacity * 2).max(64));
+        }
+        idx
+    }
+
+    fn is_set(&self, before: usize, after: usize) -> bool {
+        self.row(before)[after / 64] & (1 << (after % 64)) != 0
+    }
+
+    /// Every dense index `before` must come before, as a bitset.
+    fn row(&self, before: usize) -> &[u64] {
+        &self.bits[before * self.words..(before + 1) * self.words]
+    }
+
+    fn grow(&mut self, capacity: usize) {
+        let words = capacity.div_ceil(64);
+        let mut bits = vec![0; capacity * words];
+        for before in 0..self.capacity {
+            bits[before * words..before * words + self.words].copy_from_slice(self.row(before));
+        }
+        self.capacity = capacity;
+        self.words = words;
+        self.bits = bits;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_contains() {
+        let mut matrix = RuleMatrix::default();
+        matrix.insert(47, 53);
+        matrix.insert(97, 13);
+        matrix.insert(130, 2);
+        assert_eq!(matrix.len(), 6);
+        assert!(matrix.contains(47, 53));
+        assert!(matrix.contains(97, 13));
+        assert!(matrix.contains(130, 2));
+        assert!(!matrix.contains(53, 47));
+        assert!(!matrix.contains(200, 2));
+        matrix.remove(97, 13);
+        matrix.remove(200, 2);
+        assert!(!matrix.contains(97, 13));
+        assert_eq!(matrix.iter().collect::<Vec<_>>(), vec![(47, 53), (130, 2)]);
+        assert_eq!(matrix.pages(), vec![2, 47, 53, 130]);
+    }
+
+    #[test]
+    fn test_successors() {
+        let mut matrix = RuleMatrix::default();
+        matrix.insert(1, 3);
+        matrix.insert(1, 2);
+        matrix.insert(1, 3);
+        matrix.insert(2, 300);
+        assert_eq!(matrix.successors(1).collect::<Vec<_>>(), vec![3, 2]);
+        assert_eq!(matrix.successors(2).collect::<Vec<_>>(), vec![300]);
+        assert_eq!(matrix.successors(3).count(), 0);
+        assert_eq!(matrix.successors(4).count(), 0);
+    }
+
+    #[test]
+    fn test_large_page_ids() {
+        let mut matrix = RuleMatrix::default();
+        matrix.insert(1, 1_000_000);
+        matrix.insert(usize::MAX, 1);
+        assert_eq!(matrix.len(), 3);
+        assert!(matrix.contains(1, 1_000_000));
+        assert!(matrix.contains(usize::MAX, 1));
+        assert!(!matrix.contains(1_000_000, 1));
+        assert!(matrix.is_ordered(&[usize::MAX, 1, 1_000_000]));
+        assert!(!matrix.is_ordered(&[1_000_000, 1]));
+        assert_eq!(
+            matrix.iter().collect::<Vec<_>>(),
+            vec![(1, 1_000_000), (usize::MAX, 1)]
+        );
+    }
+
+    #[test]
+    fn test_growth() {
+        let mut matrix = RuleMatrix::default();
+        for page in 0..200 {
+            matrix.insert(page, page + 1);
+        }
+        assert_eq!(matrix.len(), 201);
+        assert!((0..200).all(|page| matrix.contains(page, page + 1)));
+        assert_eq!(matrix.iter().count(), 200);
+    }
+
+    #[test]
+    fn test_is_ordered() {
+        let mut matrix = RuleMatrix::default();
+        matrix.insert(1, 2);
+        matrix.insert(2, 70);
+        assert!(matrix.is_ordered(&[1, 2, 70]));
+        assert!(matrix.is_ordered(&[1, 70]));
+        assert!(matrix.is_ordered(&[5, 1, 300, 2]));
+        assert!(!matrix.is_ordered(&[70, 5, 2]));
+        assert!(!matrix.is_ordered(&[2, 1]));
+    }
+}
@@ -1,61 +1,417 @@
+mod closure;
+mod generator;
+mod graph;
+mod matrix;
+
+use crate::utils::{json_array, json_object};
+use closure::ClosureIndex;
+use graph::{CycleError, OrderingAnalysis, RuleGraph};
 use itertools::Itertools;
-use std::collections::HashSet;
+use matrix::RuleMatrix;
+use std::cell::OnceCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+/// Which rules an update is validated against.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ValidationMode {
+    /// Only the rules given in the input.
+    Direct,
+    /// Every rule implied by chaining the given rules together.
+    Implied,
+}
+
+/// A rule `before|after` broken by an update, i.e. `after` is printed ahead of `before`.
+#[derive(Debug, PartialEq)]
+struct Violation {
+    rule: (usize, usize),
+    before_position: usize,
+    after_position: usize,
+}
+
+/// Moves `page` from position `from` in the update to position `to` in the corrected update.
+#[derive(Debug, PartialEq)]
+struct Move {
+    page: usize,
+    from: usize,
+    to: usize,
+}
+
+/// Why an update is invalid and how to correct it. Applying the moves means taking every moved
+/// page out of the update then inserting them at their `to` positions in ascending order.
+#[derive(Debug, PartialEq)]
+struct Explanation {
+    violations: Vec<Violation>,
+    moves: Vec<Move>,
+}
+
+/// Cached outcome for a single update, kept in step with rule and update edits.
+#[derive(Debug, Clone, PartialEq)]
+struct UpdateStatus {
+    valid: bool,
+    /// Middle page of the update, once reordered if it is invalid.
+    middle: Result<usize, CycleError>,
+}
+
+/// Updates need a middle page, so must have at least one page.
+#[derive(Debug, PartialEq)]
+struct EmptyUpdateError;
+
+impl fmt::Display for EmptyUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "update has no pages")
+    }
+}
+
+impl Error for EmptyUpdateError {}
+
+/// Rules present in one rule set but not another.
+#[derive(Debug, PartialEq)]
+struct RuleDiff {
+    added: Vec<(usize, usize)>,
+    removed: Vec<(usize, usize)>,
+}
 
 struct PageUpdates {
-    rules: HashSet<(usize, usize)>,
+    /// The rules as given, in order and including duplicates, so the input can be written back.
+    rule_lines: Vec<(usize, usize)>,
+    /// Which rules hold, the rule lines only record how they were written.
+    rules: RuleMatrix,
+    /// Built on first use by `ValidationMode::Implied`, fails if the rules contain a cycle.
+    closure: OnceCell<Result<ClosureIndex, CycleError>>,
     updates: Vec<Vec<usize>>,
+    statuses: Vec<UpdateStatus>,
+    /// Running totals for `validate` and `fix`, along with the number of updates that can't be
+    /// fixed because their rules contain a cycle.
+    valid_sum: usize,
+    fixed_sum: usize,
+    cycles: usize,
 }
 
 impl PageUpdates {
+    fn new(rules: &[(usize, usize)], updates: Vec<Vec<usize>>) -> Result<Self, EmptyUpdateError> {
+        let mut page_updates = Self {
+            rule_lines: rules.to_vec(),
+            rules: RuleMatrix::default(),
+            closure: OnceCell::new(),
+            updates: Vec::with_capacity(updates.len()),
+            statuses: Vec::with_capacity(updates.len()),
+            valid_sum: 0,
+            fixed_sum: 0,
+            cycles: 0,
+        };
+        for &(before, after) in rules {
+            page_updates.rules.insert(before, after);
+        }
+        for update in updates {
+            page_updates.add_update(update)?;
+        }
+        Ok(page_updates)
+    }
+
     fn validate(&self) -> usize {
+        self.valid_sum
+    }
+
+    /// Sums the middle pages of the updates valid under `mode`, implied rules fail if the rules
+    /// contain a cycle anywhere.
+    fn validate_with(&self, mode: ValidationMode) -> Result<usize, CycleError> {
         let mut sum = 0;
         for update in &self.updates {
-            if self.is_update_valid(update) {
-                sum += update[update.len().div_euclid(2)];
+            if self.is_update_valid_with(update, mode)? {
+                sum += middle(update);
             }
         }
-        sum
+        Ok(sum)
     }
 
-    fn fix(&self) -> usize {
-        let mut sum = 0;
-        for update in &self.updates {
-            if !self.is_update_valid(update) {
-                let fixed = self.sort_update(update);
-                sum += fixed[fixed.len().div_euclid(2)];
+    /// Sums the middle pages of the invalid updates once reordered, fails if the rules between
+    /// an update's pages contain a cycle.
+    fn fix(&self) -> Result<usize, CycleError> {
+        if self.cycles > 0 {
+            if let Some(Err(e)) = self.statuses.iter().map(|s| &s.middle).find(|m| m.is_err()) {
+                return Err(e.clone());
             }
         }
-        sum
+        Ok(self.fixed_sum)
+    }
+
+    /// Adds a rule, re-checking only the updates that contain both of its pages.
+    fn add_rule(&mut self, before: usize, after: usize) {
+        if self.rules.contains(before, after) {
+            return;
+        }
+        self.rule_lines.push((before, after));
+        self.rules.insert(before, after);
+        self.rules_changed(before, after);
+    }
+
+    /// Removes a rule, re-checking only the updates that contain both of its pages.
+    fn remove_rule(&mut self, before: usize, after: usize) {
+        if !self.rules.contains(before, after) {
+            return;
+        }
+        self.rule_lines.retain(|&rule| rule != (before, after));
+        self.rules.remove(before, after);
+        self.rules_changed(before, after);
+    }
+
+    /// Adds an update, checking it against the current rules.
+    fn add_update(&mut self, update: Vec<usize>) -> Result<(), EmptyUpdateError> {
+        if update.is_empty() {
+            return Err(EmptyUpdateError);
+        }
+        let status = self.check_update(&update);
+        self.tally(&status, true);
+        self.updates.push(update);
+        self.statuses.push(status);
+        Ok(())
+    }
+
+    fn remove_update(&mut self, idx: usize) -> Vec<usize> {
+        let status = self.statuses.remove(idx);
+        self.tally(&status, false);
+        self.updates.remove(idx)
+    }
+
+    fn rules_changed(&mut self, before: usize, after: usize) {
+        self.closure.take();
+        for idx in 0..self.updates.len() {
+            let update = &self.updates[idx];
+            if update.contains(&before) && update.contains(&after) {
+                let status = self.check_update(update);
+                let previous = std::mem::replace(&mut self.statuses[idx], status.clone());
+                self.tally(&previous, false);
+                self.tally(&status, true);
+            }
+        }
+    }
+
+    fn check_update(&self, update: &[usize]) -> UpdateStatus {
+        let valid = self.is_update_valid(update);
+        let middle = if valid {
+            Ok(middle(update))
+        } else {
+            self.sort_update(update).map(|fixed| middle(&fixed))
+        };
+        UpdateStatus { valid, middle }
+    }
+
+    /// Adds the status to, or removes it from, the running totals.
+    fn tally(&mut self, status: &UpdateStatus, add: bool) {
+        let (sum, value) = match (status.valid, &status.middle) {
+            (true, Ok(page)) => (&mut self.valid_sum, *page),
+            (false, Ok(page)) => (&mut self.fixed_sum, *page),
+            (_, Err(_)) => (&mut self.cycles, 1),
+        };
+        if add {
+            *sum += value;
+        } else {
+            *sum -= value;
+        }
+    }
+
+    /// The same rule set sorted with duplicates removed, updates are left as they are.
+    fn canonical(&self) -> PageUpdates {
+        let rules: Vec<(usize, usize)> = self.rules.iter().collect();
+        PageUpdates::new(&rules, self.updates.clone()).expect("updates are checked when added")
+    }
+
+    /// Rules that `other` adds to or removes from this rule set.
+    fn rule_diff(&self, other: &PageUpdates) -> RuleDiff {
+        RuleDiff {
+            added: other
+                .rules
+                .iter()
+                .filter(|&(before, after)| !self.rules.contains(before, after))
+                .collect(),
+            removed: self
+                .rules
+                .iter()
+                .filter(|&(before, after)| !other.rules.contains(before, after))
+                .collect(),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let rules = json_array(
+            self.rule_lines
+                .iter()
+                .map(|&(before, after)| json_array([before, after])),
+        );
+        let updates = json_array(self.updates.iter().map(json_array));
+        json_object([("rules", rules), ("updates", updates)])
     }
 
     fn is_update_valid(&self, update: &[usize]) -> bool {
-        !update
-            .iter()
-            .tuple_combinations()
-            .any(|(&l, &r)| self.rules.contains(&(r, l)))
+        self.rules.is_ordered(update)
+    }
+
+    fn is_update_valid_with(
+        &self,
+        update: &[usize],
+        mode: ValidationMode,
+    ) -> Result<bool, CycleError> {
+        match mode {
+            ValidationMode::Direct => Ok(self.is_update_valid(update)),
+            ValidationMode::Implied => Ok(self.closure()?.is_ordered(update)),
+        }
     }
 
-    fn sort_update(&self, update: &[usize]) -> Vec<usize> {
+    fn closure(&self) -> Result<&ClosureIndex, CycleError> {
+        self.closure
+            .get_or_init(|| ClosureIndex::new(&self.graph()))
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// Indexes of the updates that only break implied rules, i.e. are valid under
+    /// `ValidationMode::Direct` but not `ValidationMode::Implied`.
+    fn mode_disagreements(&self) -> Result<Vec<usize>, CycleError> {
+        let closure = self.closure()?;
+        Ok((0..self.updates.len())
+            .filter(|&idx| {
+                let update = &self.updates[idx];
+                self.is_update_valid(update) && !closure.is_ordered(update)
+            })
+            .collect())
+    }
+
+    /// The graph algorithms over the rules.
+    fn graph(&self) -> RuleGraph<'_> {
+        RuleGraph::new(&self.rules)
+    }
+
+    fn sort_update(&self, update: &[usize]) -> Result<Vec<usize>, CycleError> {
+        self.graph().topological_sort(update)
+    }
+
+    /// Sorts the update with the rules as a `sort_by` comparator. That's only sound when the rules
+    /// give a total order of the update's pages, as they do for the puzzle inputs, so `None` is
+    /// returned unless every pair of pages has exactly one rule between them and no three pages
+    /// form a cycle. Use `sort_update` otherwise.
+    fn sort_update_by(&self, update: &[usize]) -> Option<Vec<usize>> {
+        // with one rule per pair, the rules are acyclic exactly when the pages are followed by
+        // 0, 1, ..., n - 1 other pages
+        let mut followers = vec![0; update.len()];
+        for ((l_idx, &l), (r_idx, &r)) in update.iter().enumerate().tuple_combinations() {
+            match (self.rules.contains(l, r), self.rules.contains(r, l)) {
+                (true, false) => followers[l_idx] += 1,
+                (false, true) => followers[r_idx] += 1,
+                _ => return None,
+            }
+        }
+        followers.sort_unstable();
+        if !followers
+            .iter()
+            .enumerate()
+            .all(|(idx, &count)| idx == count)
+        {
+            return None;
+        }
         let mut sorted = update.to_owned();
-        let mut swap_in_pass = true;
-        while swap_in_pass {
-            swap_in_pass = false;
-            for idx in (1..sorted.len()).rev() {
-                let r = sorted[idx];
-                let l = sorted[idx - 1];
-                if self.rules.contains(&(r, l)) {
-                    sorted.swap(idx, idx - 1);
-                    swap_in_pass = true;
-                }
+        sorted.sort_by(|&l, &r| {
+            if self.rules.contains(l, r) {
+                Ordering::Less
+            } else {
+                Ordering::Greater
             }
+        });
+        Some(sorted)
+    }
+
+    /// Checks how many valid orders each update has, the middle page is only well defined for
+    /// updates with a single order, or where every order shares the same middle page.
+    fn analyse_orderings(&self) -> Result<Vec<OrderingAnalysis>, CycleError> {
+        self.updates
+            .iter()
+            .map(|update| self.graph().analyse_ordering(update))
+            .collect()
+    }
+
+    /// Lists every rule the update breaks, along with the fewest page moves that turn the update
+    /// into the order given by `sort_update`.
+    fn explain(&self, update: &[usize]) -> Result<Explanation, CycleError> {
+        let mut violations: Vec<Violation> = update
+            .iter()
+            .enumerate()
+            .tuple_combinations()
+            .filter(|&((_, &after), (_, &before))| self.rules.contains(before, after))
+            .map(
+                |((after_position, &after), (before_position, &before))| Violation {
+                    rule: (before, after),
+                    before_position,
+                    after_position,
+                },
+            )
+            .collect();
+        violations.sort_by_key(|v| (v.before_position, v.after_position));
+
+        let fixed = self.sort_update(update)?;
+        let ranks: HashMap<usize, usize> = fixed
+            .iter()
+            .enumerate()
+            .map(|(rank, &page)| (page, rank))
+            .collect();
+        let ranks: Vec<usize> = update.iter().map(|page| ranks[page]).collect();
+        let kept = longest_increasing_subsequence(&ranks);
+        let moves = (0..update.len())
+            .filter(|idx| !kept.contains(idx))
+            .map(|from| Move {
+                page: update[from],
+                from,
+                to: ranks[from],
+            })
+            .collect();
+
+        Ok(Explanation { violations, moves })
+    }
+}
+
+/// Writes the puzzle format back out, `parse` reads it back to the same rules and updates.
+impl fmt::Display for PageUpdates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (before, after) in &self.rule_lines {
+            writeln!(f, "{before}|{after}")?;
         }
-        sorted
+        writeln!(f)?;
+        write!(
+            f,
+            "{}",
+            self.updates.iter().map(|u| u.iter().join(",")).join("\n")
+        )
+    }
+}
+
+fn middle(update: &[usize]) -> usize {
+    update[update.len().div_euclid(2)]
+}
+
+/// Indexes of a longest strictly increasing subsequence of `values`, O(n²) as updates are short.
+fn longest_increasing_subsequence(values: &[usize]) -> HashSet<usize> {
+    // (length of the longest subsequence ending at idx, previous idx in that subsequence)
+    let mut best: Vec<(usize, Option<usize>)> = Vec::with_capacity(values.len());
+    for (idx, value) in values.iter().enumerate() {
+        let previous = (0..idx)
+            .filter(|&prev| values[prev] < *value)
+            .max_by_key(|&prev| best[prev].0);
+        best.push((previous.map_or(1, |prev| best[prev].0 + 1), previous));
+    }
+
+    let mut subsequence = HashSet::new();
+    let mut next = (0..values.len()).max_by_key(|&idx| best[idx].0);
+    while let Some(idx) = next {
+        subsequence.insert(idx);
+        next = best[idx].1;
     }
+    subsequence
 }
 
 fn parse(input: &str) -> PageUpdates {
     let (rules, updates) = input.split_once("\n\n").unwrap();
-    let rules: HashSet<(usize, usize)> = rules
+    let rules: Vec<(usize, usize)> = rules
         .split_whitespace()
         .map(|line| line.split_once('|').unwrap())
         .map(|(l, r)| (l.parse::<usize>().unwrap(), r.parse::<usize>().unwrap()))
@@ -68,20 +424,47 @@ fn parse(input: &str) -> PageUpdates {
                 .collect()
         })
         .collect();
-    PageUpdates { rules, updates }
+    PageUpdates::new(&rules, updates).expect("split_whitespace never yields an empty update")
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::day5::parse;
-    use crate::utils::{load_file, load_input_for_day};
+    use super::*;
+    use crate::day5::graph::OrderingCount;
+    use crate::utils::{bench, load_file, load_input_for_day};
+
+    /// Checks every pair of pages against the rules.
+    fn is_update_valid_pairwise(rules: &HashSet<(usize, usize)>, update: &[usize]) -> bool {
+        !update
+            .iter()
+            .tuple_combinations()
+            .any(|(&l, &r)| rules.contains(&(r, l)))
+    }
+
+    /// Swaps neighbouring pages the rules say are out of order until a pass makes no swaps.
+    fn sort_update_bubble(rules: &HashSet<(usize, usize)>, update: &[usize]) -> Vec<usize> {
+        let mut sorted = update.to_owned();
+        let mut swap_in_pass = true;
+        while swap_in_pass {
+            swap_in_pass = false;
+            for idx in (1..sorted.len()).rev() {
+                let r = sorted[idx];
+                let l = sorted[idx - 1];
+                if rules.contains(&(r, l)) {
+                    sorted.swap(idx, idx - 1);
+                    swap_in_pass = true;
+                }
+            }
+        }
+        sorted
+    }
 
     #[test]
     fn test_parse() {
         let contents = load_file(5, "input_test.txt");
         let page_updates = parse(contents.as_str());
-        assert!(page_updates.rules.contains(&(47, 53)));
-        assert!(page_updates.rules.contains(&(53, 13)));
+        assert!(page_updates.rules.contains(47, 53));
+        assert!(page_updates.rules.contains(53, 13));
         assert_eq!(
             page_updates.updates.first(),
             Some(vec![75, 47, 61, 53, 29]).as_ref()
@@ -103,7 +486,7 @@ mod tests {
     fn test_example_part_2() {
         let contents = load_file(5, "input_test.txt");
         let page_updates = parse(contents.as_str());
-        assert_eq!(page_updates.fix(), 123);
+        assert_eq!(page_updates.fix(), Ok(123));
     }
 
     #[test]
@@ -111,30 +494,425 @@ mod tests {
         let contents = load_file(5, "input_test.txt");
         let page_updates = parse(contents.as_str());
         assert_eq!(
-            page_updates.sort_update(&vec![75, 97, 47, 61, 53]),
-            vec![97, 75, 47, 61, 53]
+            page_updates.sort_update(&[75, 97, 47, 61, 53]),
+            Ok(vec![97, 75, 47, 61, 53])
         );
         assert_eq!(
-            page_updates.sort_update(&vec![61, 13, 29]),
-            vec![61, 29, 13]
+            page_updates.sort_update(&[61, 13, 29]),
+            Ok(vec![61, 29, 13])
         );
         assert_eq!(
-            page_updates.sort_update(&vec![97, 13, 75, 29, 47]),
-            vec![97, 75, 47, 29, 13]
+            page_updates.sort_update(&[97, 13, 75, 29, 47]),
+            Ok(vec![97, 75, 47, 29, 13])
         );
     }
 
     #[test]
-    fn calculate_pt_1() {
+    fn test_matches_reference() {
+        for contents in [load_file(5, "input_test.txt"), load_input_for_day(5)] {
+            let page_updates = parse(contents.as_str());
+            let rules: HashSet<_> = page_updates.rules.iter().collect();
+            for update in &page_updates.updates {
+                let valid = is_update_valid_pairwise(&rules, update);
+                let sorted = sort_update_bubble(&rules, update);
+                assert_eq!(page_updates.is_update_valid(update), valid, "{update:?}");
+                assert_eq!(page_updates.sort_update(update), Ok(sorted.clone()));
+                assert_eq!(
+                    page_updates.sort_update_by(update),
+                    Some(sorted),
+                    "{update:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_page_ids() {
+        let page_updates = parse("1|1000000\n\n1,1000000\n1000000,1");
+        assert_eq!(page_updates.validate(), 1000000);
+        assert_eq!(page_updates.fix(), Ok(1000000));
+    }
+
+    #[test]
+    fn test_sort_update_by_partial_rules() {
+        // 2 and 3 have no rule between them
+        let page_updates = parse("1|2\n1|3\n\n3,2,1");
+        assert_eq!(page_updates.sort_update_by(&[3, 2, 1]), None);
+        // every pair has a rule but they form a cycle
+        let page_updates = parse("1|2\n2|3\n3|1\n\n1,2,3");
+        assert_eq!(page_updates.sort_update_by(&[1, 2, 3]), None);
+        let page_updates = parse("1|2\n2|3\n1|3\n\n3,1,2");
+        assert_eq!(page_updates.sort_update_by(&[3, 1, 2]), Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_rule_representations() {
+        let contents = load_input_for_day(5);
+        let page_updates = parse(contents.as_str());
+        let rules: HashSet<_> = page_updates.rules.iter().collect();
+        let updates = &page_updates.updates;
+        bench("validate hashset", 1000, || {
+            updates
+                .iter()
+                .filter(|u| is_update_valid_pairwise(&rules, u))
+                .count()
+        });
+        bench("validate matrix", 1000, || {
+            updates
+                .iter()
+                .filter(|u| page_updates.is_update_valid(u))
+                .count()
+        });
+        bench("sort hashset bubble", 1000, || {
+            updates
+                .iter()
+                .map(|u| sort_update_bubble(&rules, u))
+                .collect::<Vec<_>>()
+        });
+        bench("sort graph topological", 1000, || {
+            updates
+                .iter()
+                .map(|u| page_updates.sort_update(u))
+                .collect::<Vec<_>>()
+        });
+        bench("sort matrix comparator", 1000, || {
+            updates
+                .iter()
+                .map(|u| page_updates.sort_update_by(u))
+                .collect::<Vec<_>>()
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_generated_input() {
+        let generated = generator::generate(&generator::GeneratorConfig {
+            pages: 1000,
+            updates: 2000,
+            update_length: 50..=200,
+            ..Default::default()
+        });
+        let page_updates = parse(&generated.input);
+        let rules: HashSet<_> = page_updates.rules.iter().collect();
+        assert_eq!(page_updates.validate(), generated.validate);
+        assert_eq!(page_updates.fix(), Ok(generated.fix));
+        let updates = &page_updates.updates;
+        bench("sort hashset bubble", 1, || {
+            updates
+                .iter()
+                .map(|u| sort_update_bubble(&rules, u))
+                .collect::<Vec<_>>()
+        });
+        bench("sort graph topological", 1, || {
+            updates
+                .iter()
+                .map(|u| page_updates.sort_update(u))
+                .collect::<Vec<_>>()
+        });
+        bench("sort matrix comparator", 1, || {
+            updates
+                .iter()
+                .map(|u| page_updates.sort_update_by(u))
+                .collect::<Vec<_>>()
+        });
+    }
+
+    fn apply_moves(update: &[usize], moves: &[Move]) -> Vec<usize> {
+        let mut applied: Vec<usize> = update
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !moves.iter().any(|m| m.from == *idx))
+            .map(|(_, &page)| page)
+            .collect();
+        let mut moves: Vec<&Move> = moves.iter().collect();
+        moves.sort_by_key(|m| m.to);
+        for m in moves {
+            applied.insert(m.to, m.page);
+        }
+        applied
+    }
+
+    #[test]
+    fn test_explain() {
+        let contents = load_file(5, "input_test.txt");
+        let page_updates = parse(contents.as_str());
+        assert_eq!(
+            page_updates.explain(&[75, 47, 61, 53, 29]),
+            Ok(Explanation {
+                violations: vec![],
+                moves: vec![]
+            })
+        );
+        assert_eq!(
+            page_updates.explain(&[61, 13, 29]),
+            Ok(Explanation {
+                violations: vec![Violation {
+                    rule: (29, 13),
+                    before_position: 2,
+                    after_position: 1
+                }],
+                moves: vec![Move {
+                    page: 13,
+                    from: 1,
+                    to: 2
+                }]
+            })
+        );
+        let explanation = page_updates.explain(&[97, 13, 75, 29, 47]).unwrap();
+        assert_eq!(
+            explanation
+                .violations
+                .iter()
+                .map(|v| v.rule)
+                .collect::<Vec<_>>(),
+            vec![(75, 13), (29, 13), (47, 13), (47, 29)]
+        );
+        assert_eq!(explanation.moves.len(), 2);
+    }
+
+    #[test]
+    fn test_explain_input() {
         let contents = load_input_for_day(5);
         let page_updates = parse(contents.as_str());
+        for update in &page_updates.updates {
+            let explanation = page_updates.explain(update).unwrap();
+            assert_eq!(
+                explanation.violations.is_empty(),
+                page_updates.is_update_valid(update)
+            );
+            assert_eq!(
+                apply_moves(update, &explanation.moves),
+                page_updates.sort_update(update).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_analyse_orderings() {
+        let contents = load_file(5, "input_test.txt");
+        let page_updates = parse(contents.as_str());
+        let analyses = page_updates.analyse_orderings().unwrap();
+        assert!(analyses.iter().all(OrderingAnalysis::is_unique));
+
+        let page_updates = parse("1|2\n1|3\n\n1,2,3\n2,1");
+        let analyses = page_updates.analyse_orderings().unwrap();
+        assert_eq!(analyses[0].count, OrderingCount::Exact(2));
+        assert_eq!(analyses[0].middle_pages.len(), 2);
+        assert!(analyses[1].is_unique());
+    }
+
+    #[test]
+    fn test_input_orderings_unique() {
+        let contents = load_input_for_day(5);
+        let page_updates = parse(contents.as_str());
+        let analyses = page_updates.analyse_orderings().unwrap();
+        assert!(analyses.iter().all(OrderingAnalysis::is_unique));
+    }
+
+    #[test]
+    fn test_validate_implied() {
+        let contents = load_file(5, "input_test.txt");
+        let page_updates = parse(contents.as_str());
+        assert_eq!(page_updates.validate_with(ValidationMode::Implied), Ok(143));
+        assert_eq!(page_updates.mode_disagreements(), Ok(vec![]));
+
+        // 1 before 3 is only implied, so the second update is valid by the direct rules alone
+        let page_updates = parse("1|2\n2|3\n\n1,2,3\n3,1");
+        assert_eq!(
+            page_updates.validate_with(ValidationMode::Direct),
+            Ok(2 + 1)
+        );
+        assert_eq!(page_updates.validate_with(ValidationMode::Implied), Ok(2));
+        assert_eq!(page_updates.mode_disagreements(), Ok(vec![1]));
+    }
+
+    #[test]
+    fn test_validate_implied_cycle() {
+        let mut page_updates = parse("1|2\n2|3\n\n1,2,3\n3,1");
+        page_updates.add_rule(3, 1);
+        let cycle = Err(CycleError {
+            cycle: vec![1, 2, 3],
+        });
+        assert_eq!(page_updates.validate_with(ValidationMode::Implied), cycle);
+        assert_eq!(page_updates.mode_disagreements(), cycle.map(|_| vec![]));
+        // 1,2,3 breaks 3|1 while 3,1 now follows it
+        assert_eq!(page_updates.validate_with(ValidationMode::Direct), Ok(1));
+        page_updates.remove_rule(3, 1);
+        assert_eq!(page_updates.validate_with(ValidationMode::Implied), Ok(2));
+    }
+
+    #[test]
+    fn test_input_validate_implied() {
+        // the full rule set is cyclic, so there are no implied rules to check against
+        let contents = load_input_for_day(5);
+        let page_updates = parse(contents.as_str());
+        let cycle = page_updates.graph().find_cycle().unwrap();
+        assert_eq!(
+            page_updates.validate_with(ValidationMode::Implied),
+            Err(CycleError { cycle })
+        );
+        assert!(page_updates.mode_disagreements().is_err());
+        assert_eq!(
+            page_updates.validate_with(ValidationMode::Direct),
+            Ok(page_updates.validate())
+        );
+    }
+
+    #[test]
+    fn test_edit_rules() {
+        let contents = load_file(5, "input_test.txt");
+        let mut page_updates = parse(contents.as_str());
+        // 75,47,61,53,29 is only valid while 47 may come before 75
+        page_updates.add_rule(47, 75);
+        assert_eq!(page_updates.validate(), 143 - 61);
+        assert!(page_updates.fix().is_err());
+        page_updates.remove_rule(75, 47);
+        assert_eq!(page_updates.validate(), 143 - 61);
+        // the first update is now fixed with 61 in the middle, and two fixed updates swap 47
+        // in the middle for 75
+        assert_eq!(page_updates.fix(), Ok(123 + 61 + 2 * (75 - 47)));
+        page_updates.remove_rule(47, 75);
+        page_updates.add_rule(75, 47);
+        assert_eq!(page_updates.validate(), 143);
+        assert_eq!(page_updates.fix(), Ok(123));
+    }
+
+    #[test]
+    fn test_edit_updates() {
+        let contents = load_file(5, "input_test.txt");
+        let mut page_updates = parse(contents.as_str());
+        assert_eq!(page_updates.remove_update(0), vec![75, 47, 61, 53, 29]);
+        assert_eq!(page_updates.validate(), 143 - 61);
+        page_updates.add_update(vec![13, 29, 53]).unwrap();
+        assert_eq!(page_updates.fix(), Ok(123 + 29));
+        page_updates.add_update(vec![97, 61]).unwrap();
+        assert_eq!(page_updates.validate(), 143 - 61 + 61);
+    }
+
+    #[test]
+    fn test_add_empty_update() {
+        let contents = load_file(5, "input_test.txt");
+        let mut page_updates = parse(contents.as_str());
+        assert_eq!(page_updates.add_update(vec![]), Err(EmptyUpdateError));
+        assert_eq!(page_updates.updates.len(), 6);
         assert_eq!(page_updates.validate(), 143);
+        assert_eq!(
+            PageUpdates::new(&[(1, 2)], vec![vec![1, 2], vec![]]).err(),
+            Some(EmptyUpdateError)
+        );
+    }
+
+    #[test]
+    fn test_edits_match_rebuild() {
+        let contents = load_input_for_day(5);
+        let mut page_updates = parse(contents.as_str());
+        let rules: Vec<(usize, usize)> = page_updates.rules.iter().collect();
+        for &(before, after) in rules.iter().step_by(3) {
+            page_updates.remove_rule(before, after);
+        }
+        for &(before, after) in rules.iter().step_by(6) {
+            page_updates.add_rule(before, after);
+        }
+        for idx in (0..page_updates.updates.len()).step_by(5).rev() {
+            page_updates.remove_update(idx);
+        }
+        page_updates.add_update(vec![13, 22, 31]).unwrap();
+
+        let rules: Vec<(usize, usize)> = page_updates.rules.iter().collect();
+        let rebuilt = PageUpdates::new(&rules, page_updates.updates.clone()).unwrap();
+        assert_eq!(page_updates.statuses, rebuilt.statuses);
+        assert_eq!(page_updates.validate(), rebuilt.validate());
+        assert_eq!(page_updates.fix(), rebuilt.fix());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for contents in [load_file(5, "input_test.txt"), load_input_for_day(5)] {
+            let page_updates = parse(contents.as_str());
+            assert_eq!(page_updates.to_string(), contents);
+        }
+        let contents = "2|1\n1|3\n2|1\n\n1,2\n3";
+        assert_eq!(parse(contents).to_string(), contents);
+    }
+
+    #[test]
+    fn test_canonical() {
+        let page_updates = parse("2|1\n1|3\n2|1\n\n1,2\n3");
+        let canonical = page_updates.canonical();
+        assert_eq!(canonical.to_string(), "1|3\n2|1\n\n1,2\n3");
+        assert_eq!(canonical.validate(), page_updates.validate());
+        assert_eq!(canonical.fix(), page_updates.fix());
+        assert_eq!(
+            parse(&canonical.to_string()).to_string(),
+            canonical.to_string()
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let page_updates = parse("2|1\n1|3\n\n1,2\n3");
+        assert_eq!(
+            page_updates.to_json(),
+            r#"{"rules":[[2,1],[1,3]],"updates":[[1,2],[3]]}"#
+        );
+    }
+
+    #[test]
+    fn test_rule_diff() {
+        let page_updates = parse("2|1\n1|3\n\n1,2");
+        let mut edited = page_updates.canonical();
+        edited.remove_rule(2, 1);
+        edited.add_rule(3, 4);
+        edited.add_rule(1, 3);
+        assert_eq!(
+            page_updates.rule_diff(&edited),
+            RuleDiff {
+                added: vec![(3, 4)],
+                removed: vec![(2, 1)]
+            }
+        );
+        assert_eq!(edited.to_string(), "1|3\n3|4\n\n1,2");
+    }
+
+    #[test]
+    fn test_fix_cycle() {
+        let page_updates = parse("1|2\n2|3\n3|1\n\n1,2\n3,2,1");
+        assert_eq!(page_updates.validate(), 2);
+        assert_eq!(
+            page_updates.fix(),
+            Err(CycleError {
+                cycle: vec![3, 1, 2]
+            })
+        );
+    }
+
+    #[test]
+    fn test_input_cycles() {
+        // the full rule set is cyclic, but the rules between any one update's pages are not
+        let contents = load_input_for_day(5);
+        let page_updates = parse(contents.as_str());
+        let cycle = page_updates.graph().find_cycle().unwrap();
+        for (idx, &before) in cycle.iter().enumerate() {
+            let after = cycle[(idx + 1) % cycle.len()];
+            assert!(page_updates.rules.contains(before, after));
+        }
+        for update in &page_updates.updates {
+            assert_eq!(page_updates.graph().find_cycle_in(update), None);
+        }
+    }
+
+    #[test]
+    fn calculate_pt_1() {
+        let contents = load_input_for_day(5);
+        let page_updates = parse(contents.as_str());
+        assert_eq!(page_updates.validate(), 5762);
     }
 
     #[test]
     fn calculate_pt_2() {
         let contents = load_input_for_day(5);
         let page_updates = parse(contents.as_str());
-        assert_eq!(page_updates.fix(), 4130);
+        assert_eq!(page_updates.fix(), Ok(4130));
     }
 }
@@ -1,4 +1,12 @@
-use std::{fs::File, io::Read, path, path::Path};
+use std::{
+    fmt::{Display, Write},
+    fs::File,
+    hint::black_box,
+    io::Read,
+    path,
+    path::Path,
+    time::{Duration, Instant},
+};
 
 pub fn load_input_for_day(day: usize) -> String {
     load_file(day, "input.txt")
@@ -20,3 +28,82 @@ pub fn load_file(day: usize, name: &str) -> String {
 
     file_contents
 }
+
+/// Runs `f` repeatedly and prints the mean time per run. Benchmarks are `#[ignore]`d tests, run
+/// them with `cargo test --release -- --ignored --nocapture`.
+pub fn bench<T>(label: &str, iterations: u32, mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(f());
+    }
+    let mean = start.elapsed() / iterations;
+    println!("{label}: {mean:?}");
+    mean
+}
+
+/// Quotes `s` as a JSON string, escaping quotes, backslashes and control characters.
+pub fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Joins values that are already JSON into an array.
+pub fn json_array<T: Display>(values: impl IntoIterator<Item = T>) -> String {
+    let values: Vec<String> = values.into_iter().map(|value| value.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+/// Builds an object from keys, quoted as strings, and values that are already JSON.
+pub fn json_object<K: Display, V: Display>(entries: impl IntoIterator<Item = (K, V)>) -> String {
+    let entries: Vec<String> = entries
+        .into_iter()
+        .map(|(key, value)| format!("{}:{value}", json_string(&key.to_string())))
+        .collect();
+    format!("{{{}}}", entries.join(","))
+}
+
+/// Small seeded generator (SplitMix64) for reproducible generated inputs, not for anything that
+/// needs good statistical quality.
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A value in `0..bound`, slightly biased for bounds that don't divide 2^64.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    pub fn chance(&mut self, probability: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for idx in (1..items.len()).rev() {
+            items.swap(idx, self.below(idx + 1));
+        }
+    }
+}